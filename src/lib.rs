@@ -4,13 +4,20 @@ use bitfield::bitfield;
 use embedded_hal::blocking::delay::DelayMs;
 
 pub mod submodules;
-pub use crate::submodules::registers::Oversampling;
-use crate::submodules::registers::{Mode, RawMeasures, Status};
+#[cfg(feature = "float")]
+pub use crate::submodules::float::MeasurementF32;
+pub use crate::submodules::registers::{Filter, Oversampling, StandbyPeriod};
+use crate::submodules::registers::{Mode, RawMeasures};
+use crate::submodules::shared::{
+    Access, Init, Measure, PollMeasurement, ReadCalib, ReadChip, ReadRaw, Reconfigure, Sequence,
+    SetMode, SoftReset, Step, MAX_READ_LEN,
+};
 use submodules::registers::{
-    Calib00_25, Calib00_25Arr, Calib26_41, Calib26_41Arr, CtrlHum, CtrlMeas, Id, RegAddr, RegSize,
-    Reset,
+    Calib00_25, Calib00_25Arr, Calib26_41, Calib26_41Arr, Config, CtrlHum, CtrlMeas, Id, RegSize,
 };
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CalibData {
     pub dig_t1: u16,
     pub dig_t2: i16,
@@ -58,6 +65,74 @@ impl CalibData {
             dig_h6: calib26_41.get_dig_h6(),
         }
     }
+
+    /// Plausibility check catching reads from floating or shorted bus (all 0x00 or 0xFF),
+    /// coefficients the compensation formulas can't work with and ones outside of values seen
+    /// on real units. Humidity coefficients are only checked on chips with humidity sensor
+    pub fn is_valid(&self, chip: ChipVariant) -> bool {
+        let bytes = self.to_bytes();
+        let blank =
+            |block: &[u8]| block.iter().all(|&b| b == 0x00) || block.iter().all(|&b| b == 0xFF);
+        // Temperature and pressure coefficients, present on both BME280 and BMP280
+        if blank(&bytes[..24]) {
+            return false;
+        }
+        // Humidity coefficients at 0xE1..=0xE7, read separately from the ones above
+        if chip.has_humidity() && blank(&bytes[Calib00_25Arr::REG_SIZE..][..7]) {
+            return false;
+        }
+
+        // Bands around BMP280 datasheet example (chapter 3.12: dig_T1 27504, dig_T2 26435,
+        // dig_P1 36477, dig_P2 -10685) hold on both chips and catch corrupted NVM words. They
+        // also rule out zero dig_T1, and zero dig_P1 pressure compensation divides by
+        (19000..=35000).contains(&self.dig_t1)
+            && self.dig_t2 > 0
+            && (30000..=42000).contains(&self.dig_p1)
+            && self.dig_p2 < 0
+    }
+
+    /// Length of calibration registers, 0x88..=0xA1 followed by 0xE1..=0xF0
+    pub const BYTES_LEN: usize = Calib00_25Arr::REG_SIZE + Calib26_41Arr::REG_SIZE;
+
+    /// Parse calibration in the chip's register layout, as returned by [`Self::to_bytes`]
+    pub fn from_bytes(bytes: &[u8; Self::BYTES_LEN]) -> Self {
+        let (calib00_25, calib26_41) = bytes.split_at(Calib00_25Arr::REG_SIZE);
+        Self::new(Calib00_25(calib00_25), Calib26_41(calib26_41))
+    }
+
+    /// Calibration in the chip's register layout, reserved bits are zeroed
+    pub fn to_bytes(&self) -> [u8; Self::BYTES_LEN] {
+        let mut bytes = [0; Self::BYTES_LEN];
+        let words = [
+            self.dig_t1 as i16,
+            self.dig_t2,
+            self.dig_t3,
+            self.dig_p1 as i16,
+            self.dig_p2,
+            self.dig_p3,
+            self.dig_p4,
+            self.dig_p5,
+            self.dig_p6,
+            self.dig_p7,
+            self.dig_p8,
+            self.dig_p9,
+        ];
+        for (chunk, word) in bytes.chunks_exact_mut(2).zip(words) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        bytes[25] = self.dig_h1;
+
+        let calib26_41 = &mut bytes[Calib00_25Arr::REG_SIZE..];
+        calib26_41[0..2].copy_from_slice(&self.dig_h2.to_le_bytes());
+        calib26_41[2] = self.dig_h3;
+        // dig_h4 and dig_h5 are 12 bits, sharing nibbles of 0xE5
+        calib26_41[3] = (self.dig_h4 >> 4) as u8;
+        calib26_41[4] = (self.dig_h4 & 0x0F) as u8 | ((self.dig_h5 & 0x0F) << 4) as u8;
+        calib26_41[5] = (self.dig_h5 >> 4) as u8;
+        calib26_41[6] = self.dig_h6 as u8;
+
+        bytes
+    }
 }
 
 pub trait RegRead {
@@ -68,28 +143,388 @@ pub trait RegRead {
 
 pub trait RegWrite {
     type Error;
+    /// Transport is 3-wire SPI, which has to be enabled in sensor before any reads
+    const SPI_3W: bool = false;
 
     fn reg_write(&mut self, dev_addr: u8, reg_addr: u8, data: u8) -> Result<(), Self::Error>;
 }
 
+/// Lends interface or delay to the driver, see [`DetachedBme280`]
+pub struct Borrowed<'a, T>(pub &'a mut T);
+
+impl<T: RegRead> RegRead for Borrowed<'_, T> {
+    type Error = T::Error;
+
+    fn reg_read(&mut self, dev_addr: u8, reg_addr: u8, buf: &mut [u8]) -> Result<(), Self::Error> {
+        self.0.reg_read(dev_addr, reg_addr, buf)
+    }
+}
+
+impl<T: RegWrite> RegWrite for Borrowed<'_, T> {
+    type Error = T::Error;
+    const SPI_3W: bool = T::SPI_3W;
+
+    fn reg_write(&mut self, dev_addr: u8, reg_addr: u8, data: u8) -> Result<(), Self::Error> {
+        self.0.reg_write(dev_addr, reg_addr, data)
+    }
+}
+
+impl<T: DelayMs<u16>> DelayMs<u16> for Borrowed<'_, T> {
+    fn delay_ms(&mut self, ms: u16) {
+        self.0.delay_ms(ms);
+    }
+}
+
+#[derive(Debug)]
 pub enum Bme280Error<InterfaceE> {
     Inteface(InterfaceE),
     IdDoesNotMatch,
+    /// Sensor still reports conversion in progress after its maximum duration
+    Timeout,
+    /// Sensor still copies calibration from NVM after reset
+    NvmCopyTimeout,
+    /// Calibration read from the sensor (or passed to `init_with_calib`) is blank or
+    /// unusable, see [`CalibData::is_valid`]
+    InvalidCalibration,
+    /// Configuration rejected before touching the sensor
+    InvalidConfig(ConfigError),
+    /// Control registers read back differently from what was written
+    ConfigNotApplied,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigError {
+    /// Pressure and humidity compensation depend on temperature (`t_fine`), so it can't be
+    /// skipped while any of them is measured
+    TemperatureDisabled,
+    /// All measurements are skipped
+    NothingMeasured,
+    /// Standby period means something else on detected chip, see [`StandbyPeriod`]
+    StandbyNotSupported,
+}
+
+/// Compensated measurement, along with raw values it is computed from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Measurement {
+    /// Temperature in °C, `None` if measurement was skipped
+    pub temperature: Option<I22F10>,
+    /// Pressure in Pa, `None` if it or temperature measurement was skipped
+    pub pressure: Option<I24F8>,
+    /// Relative humidity in %, `None` if it or temperature measurement was skipped, or on
+    /// chips without humidity sensor
+    pub humidity: Option<I22F10>,
+    pub adc_t: u32,
+    pub adc_p: u32,
+    pub adc_h: u32,
+    /// Fine resolution temperature, which pressure and humidity are compensated with
+    pub t_fine: i32,
+}
+
+/// Sensor model, detected from `Id` register
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChipVariant {
+    Bme280,
+    /// Same register map as BME280, but without humidity sensor
+    Bmp280,
+}
+
+impl ChipVariant {
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            Id::BME280_STANDARD_ID => Some(ChipVariant::Bme280),
+            Id::BMP280_MASS_PRODUCTION_ID => Some(ChipVariant::Bmp280),
+            id if Id::BMP280_SAMPLE_IDS.contains(&id) => Some(ChipVariant::Bmp280),
+            _ => None,
+        }
+    }
+
+    pub fn has_humidity(self) -> bool {
+        self == ChipVariant::Bme280
+    }
+}
+
+/// I2C address of the sensor, selected by SDO pin
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Address {
+    /// SDO tied to GND
+    Primary,
+    /// SDO tied to VDDIO
+    Secondary,
+    /// E.g. sensor behind address translator
+    Custom(u8),
+}
+
+impl Address {
+    /// Addresses tried by [`submodules::i2c::probe`], in order
+    pub const PROBED: [Address; 2] = [Address::Primary, Address::Secondary];
+}
+
+impl From<Address> for u8 {
+    fn from(address: Address) -> Self {
+        match address {
+            Address::Primary => 0x76,
+            Address::Secondary => 0x77,
+            Address::Custom(address) => address,
+        }
+    }
 }
 
 pub struct Bme280<InterfaceT, DelayT> {
     interface: InterfaceT,
     dev_addr: u8,
+    chip: ChipVariant,
     calib_data: CalibData,
     delay: DelayT,
     config: Bme280Config,
 }
 
-#[derive(Default)]
+/// Driver state without interface and delay, so the bus can be shared with other drivers.
+/// Both are lent to the driver for each call through [`Self::with`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DetachedBme280 {
+    dev_addr: u8,
+    chip: ChipVariant,
+    calib_data: CalibData,
+    config: Bme280Config,
+}
+
+/// Defaults to [`Bme280Config::weather_monitoring`]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Bme280Config {
     pub hum_oversampling: Oversampling,
     pub temp_oversampling: Oversampling,
     pub press_oversampling: Oversampling,
+    /// Inactive time between conversions in normal mode
+    pub standby_period: StandbyPeriod,
+    /// IIR filter coefficient applied to temperature and pressure
+    pub filter: Filter,
+    /// Mode sensor is left in after `init`
+    pub mode: MeasurementMode,
+    /// Per-device correction of compensated output
+    pub trim: Trim,
+}
+
+impl Default for Bme280Config {
+    fn default() -> Self {
+        Self::weather_monitoring()
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MeasurementMode {
+    /// Sensor sleeps until conversion is triggered, e.g. by `do_measurement`
+    #[default]
+    Forced,
+    /// Sensor cycles between conversions and standby periods on its own
+    Normal,
+}
+
+/// Builds [`Bme280Config`] validated on [`Self::build`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bme280ConfigBuilder {
+    config: Bme280Config,
+}
+
+impl Bme280ConfigBuilder {
+    pub fn hum_oversampling(mut self, oversampling: Oversampling) -> Self {
+        self.config.hum_oversampling = oversampling;
+        self
+    }
+
+    pub fn temp_oversampling(mut self, oversampling: Oversampling) -> Self {
+        self.config.temp_oversampling = oversampling;
+        self
+    }
+
+    pub fn press_oversampling(mut self, oversampling: Oversampling) -> Self {
+        self.config.press_oversampling = oversampling;
+        self
+    }
+
+    pub fn standby_period(mut self, standby_period: StandbyPeriod) -> Self {
+        self.config.standby_period = standby_period;
+        self
+    }
+
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.config.filter = filter;
+        self
+    }
+
+    pub fn mode(mut self, mode: MeasurementMode) -> Self {
+        self.config.mode = mode;
+        self
+    }
+
+    pub fn trim(mut self, trim: Trim) -> Self {
+        self.config.trim = trim;
+        self
+    }
+
+    pub fn build(self) -> Result<Bme280Config, ConfigError> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
+/// Correction applied on top of factory calibration, e.g. for board self-heating
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Trim {
+    /// Added to temperature, in °C. Applied to `t_fine`, so pressure and humidity are
+    /// compensated with corrected temperature
+    pub temperature_offset: I22F10,
+    /// Added to pressure, in Pa
+    pub pressure_offset: I24F8,
+    /// Humidity is corrected as `humidity * humidity_gain + humidity_offset`, in %
+    pub humidity_gain: I22F10,
+    pub humidity_offset: I22F10,
+}
+
+impl Default for Trim {
+    fn default() -> Self {
+        Self {
+            temperature_offset: I22F10::new(0, 0),
+            pressure_offset: I24F8::new(0, 0),
+            humidity_gain: I22F10::new(1, 0),
+            humidity_offset: I22F10::new(0, 0),
+        }
+    }
+}
+
+impl Trim {
+    /// Returns `(gain, offset)` mapping two `measured` readings onto `reference` ones,
+    /// `None` if measured readings are equal or result overflows
+    pub fn two_point(
+        measured: (I22F10, I22F10),
+        reference: (I22F10, I22F10),
+    ) -> Option<(I22F10, I22F10)> {
+        let gain = reference
+            .1
+            .checked_sub(reference.0)?
+            .checked_div(measured.1.checked_sub(measured.0)?)?;
+        let offset = reference.0.checked_sub(measured.0.checked_mul(gain)?)?;
+        Some((gain, offset))
+    }
+}
+
+/// Duration of single conversion, in microseconds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MeasurementTime {
+    pub typical_us: u32,
+    pub max_us: u32,
+}
+
+// Use case presets from BME 280 datasheet chapter 3.5
+impl Bme280Config {
+    /// Forced mode, all channels ×1, filter off. Intended for one conversion per minute
+    pub fn weather_monitoring() -> Self {
+        Self {
+            hum_oversampling: Oversampling::X1,
+            temp_oversampling: Oversampling::X1,
+            press_oversampling: Oversampling::X1,
+            standby_period: StandbyPeriod::Us500,
+            filter: Filter::Off,
+            mode: MeasurementMode::Forced,
+            trim: Trim::default(),
+        }
+    }
+
+    /// Forced mode, pressure skipped, temperature and humidity ×1, filter off. Intended for
+    /// one conversion per second
+    pub fn humidity_sensing() -> Self {
+        Self {
+            press_oversampling: Oversampling::ModuleDisabled,
+            ..Self::weather_monitoring()
+        }
+    }
+
+    /// Normal mode with 0.5 ms standby, pressure ×16, temperature ×2, humidity ×1, filter 16
+    pub fn indoor_navigation() -> Self {
+        Self {
+            hum_oversampling: Oversampling::X1,
+            temp_oversampling: Oversampling::X2,
+            press_oversampling: Oversampling::X16,
+            standby_period: StandbyPeriod::Us500,
+            filter: Filter::C16,
+            mode: MeasurementMode::Normal,
+            trim: Trim::default(),
+        }
+    }
+
+    /// Normal mode with 0.5 ms standby, pressure ×4, temperature ×1, humidity skipped,
+    /// filter 16
+    pub fn gaming() -> Self {
+        Self {
+            hum_oversampling: Oversampling::ModuleDisabled,
+            temp_oversampling: Oversampling::X1,
+            press_oversampling: Oversampling::X4,
+            standby_period: StandbyPeriod::Us500,
+            filter: Filter::C16,
+            mode: MeasurementMode::Normal,
+            trim: Trim::default(),
+        }
+    }
+}
+
+impl Bme280Config {
+    pub fn builder() -> Bme280ConfigBuilder {
+        Self::default().into_builder()
+    }
+
+    /// Builder starting from this configuration, e.g. to adjust a preset
+    pub fn into_builder(self) -> Bme280ConfigBuilder {
+        Bme280ConfigBuilder { config: self }
+    }
+
+    /// Checks that enabled measurements can be compensated, done by `init`
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let temp_disabled = self.temp_oversampling == Oversampling::ModuleDisabled;
+        let others_enabled = self.press_oversampling != Oversampling::ModuleDisabled
+            || self.hum_oversampling != Oversampling::ModuleDisabled;
+        if temp_disabled && others_enabled {
+            return Err(ConfigError::TemperatureDisabled);
+        }
+        if temp_disabled {
+            return Err(ConfigError::NothingMeasured);
+        }
+        Ok(())
+    }
+
+    /// Conversion duration for current oversampling settings, from datasheet appendix B
+    pub fn measurement_time(&self) -> MeasurementTime {
+        let t_os = self.temp_oversampling.factor();
+        let p_os = self.press_oversampling.factor();
+        let h_os = self.hum_oversampling.factor();
+
+        let mut typical_us = 1000 + 2000 * t_os;
+        let mut max_us = 1250 + 2300 * t_os;
+        // Pressure and humidity add a fixed overhead only when enabled
+        for os in [p_os, h_os] {
+            if os != 0 {
+                typical_us += 2000 * os + 500;
+                max_us += 2300 * os + 575;
+            }
+        }
+
+        MeasurementTime { typical_us, max_us }
+    }
+
+    // Register values applying the configuration, with sensor left in sleep mode
+    pub(crate) fn registers(&self, spi3w: bool) -> (CtrlHum, CtrlMeas, Config) {
+        let mut ctrl_hum = CtrlHum(0);
+        let mut ctrl_meas = CtrlMeas(0);
+        let mut config = Config(0);
+
+        ctrl_hum.set_oversampling(self.hum_oversampling as u8);
+        ctrl_meas.set_mode(Mode::Sleep as u8);
+        ctrl_meas.set_temp_oversampling(self.temp_oversampling as u8);
+        ctrl_meas.set_press_oversampling(self.press_oversampling as u8);
+        config.set_t_sb(self.standby_period as u8);
+        config.set_filter(self.filter as u8);
+        config.set_spi3w_en(spi3w as u8);
+
+        (ctrl_hum, ctrl_meas, config)
+    }
 }
 
 impl<InterfaceT, InterfaceE, DelayT> Bme280<InterfaceT, DelayT>
@@ -98,141 +533,440 @@ where
     DelayT: DelayMs<u16>,
 {
     pub fn init(
+        interface: InterfaceT,
+        dev_addr: impl Into<u8>,
+        delay: DelayT,
+        config: Bme280Config,
+    ) -> Result<Self, Bme280Error<InterfaceE>> {
+        Self::init_inner(interface, dev_addr.into(), delay, config, None)
+    }
+
+    /// Same as [`Self::init`], but uses `calib_data` (e.g. restored from flash) instead of
+    /// reading it from the sensor
+    pub fn init_with_calib(
+        interface: InterfaceT,
+        dev_addr: impl Into<u8>,
+        delay: DelayT,
+        config: Bme280Config,
+        calib_data: CalibData,
+    ) -> Result<Self, Bme280Error<InterfaceE>> {
+        Self::init_inner(interface, dev_addr.into(), delay, config, Some(calib_data))
+    }
+
+    fn init_inner(
         mut interface: InterfaceT,
         dev_addr: u8,
         mut delay: DelayT,
         config: Bme280Config,
+        calib_data: Option<CalibData>,
     ) -> Result<Self, Bme280Error<InterfaceE>> {
-        Self::reset(&mut interface, dev_addr, &mut delay)?;
-
-        if Self::read_id(&mut interface, dev_addr)? != Id::BME280_STANDARD_ID {
-            return Err(Bme280Error::IdDoesNotMatch);
-        }
+        let init = Init::new(config, calib_data, InterfaceT::SPI_3W)?;
+        let (chip, calib_data, config) =
+            Self::run_on(&mut interface, dev_addr, Some(&mut delay), init)?;
 
-        let mut bme280 = Self {
-            calib_data: Self::read_calib(&mut interface, dev_addr)?,
+        Ok(Self {
+            calib_data,
             dev_addr,
+            chip,
             interface,
             delay,
             config,
-        };
-        bme280.apply_cfg()?;
-        Ok(bme280)
+        })
     }
 
-    fn apply_cfg(&mut self) -> Result<(), Bme280Error<InterfaceE>> {
-        let mut ctrl_meas = CtrlMeas(0);
-        let mut ctrl_hum = CtrlHum(0);
+    // Performs bus accesses `seq` asks for until it is done. Without delay waits are skipped
+    fn run_on<S: Sequence<InterfaceE>>(
+        interface: &mut InterfaceT,
+        dev_addr: u8,
+        mut delay: Option<&mut DelayT>,
+        mut seq: S,
+    ) -> Result<S::Output, Bme280Error<InterfaceE>> {
+        let mut buf = [0; MAX_READ_LEN];
+        let mut last = Ok(0);
+        loop {
+            let access = match seq.step(last.map(|len| &buf[..len]))? {
+                Step::Access(access) => access,
+                Step::Done(output) => return Ok(output),
+            };
+            last = match access {
+                Access::Read(reg_addr, len) => interface
+                    .reg_read(dev_addr, reg_addr, &mut buf[..len])
+                    .map(|()| len),
+                Access::Write(reg_addr, data) => {
+                    interface.reg_write(dev_addr, reg_addr, data).map(|()| 0)
+                }
+                Access::DelayMs(ms) => {
+                    if let Some(delay) = delay.as_deref_mut() {
+                        delay.delay_ms(ms as u16);
+                    }
+                    Ok(0)
+                }
+            }
+            .map_err(Bme280Error::Inteface);
+        }
+    }
+
+    fn run<S: Sequence<InterfaceE>>(
+        &mut self,
+        seq: S,
+    ) -> Result<S::Output, Bme280Error<InterfaceE>> {
+        Self::run_on(
+            &mut self.interface,
+            self.dev_addr,
+            Some(&mut self.delay),
+            seq,
+        )
+    }
 
-        ctrl_meas.set_temp_oversampling(self.config.temp_oversampling as u8);
-        ctrl_meas.set_press_oversampling(self.config.press_oversampling as u8);
-        ctrl_hum.set_oversampling(self.config.hum_oversampling as u8);
+    /// Apply new configuration at runtime. Sensor is put to sleep first, since config
+    /// writes may be ignored in normal mode, and restarted if `config` asks for normal mode.
+    /// On failure previous configuration is kept and applied again
+    pub fn reconfigure(&mut self, config: Bme280Config) -> Result<(), Bme280Error<InterfaceE>> {
+        let reconfigure = Reconfigure::new(config, self.config, self.chip, InterfaceT::SPI_3W)?;
+        self.config = self.run(reconfigure)?;
+        Ok(())
+    }
 
-        self.interface
-            .reg_write(self.dev_addr, CtrlMeas::START_ADDR, ctrl_meas.0)
-            .map_err(Bme280Error::Inteface)?;
-        self.interface
-            .reg_write(self.dev_addr, CtrlHum::START_ADDR, ctrl_hum.0)
-            .map_err(Bme280Error::Inteface)?;
+    /// Reads and validates calibration without initializing the driver, so reads failing with
+    /// [`Bme280Error::InvalidCalibration`] can be retried before [`Self::init_with_calib`].
+    /// With 3-wire SPI sensor answers only after it is initialized
+    pub fn read_calibration(
+        interface: &mut InterfaceT,
+        dev_addr: impl Into<u8>,
+    ) -> Result<CalibData, Bme280Error<InterfaceE>> {
+        let dev_addr = dev_addr.into();
+        let chip = Self::run_on(interface, dev_addr, None, ReadChip::new())?;
+        Self::run_on(interface, dev_addr, None, ReadCalib::new(chip))
+    }
 
+    /// Reads calibration again, current one is kept if read fails
+    pub fn reload_calib(&mut self) -> Result<(), Bme280Error<InterfaceE>> {
+        self.calib_data = self.run(ReadCalib::new(self.chip))?;
         Ok(())
     }
 
-    fn set_mode(&mut self, mode: Mode) -> Result<(), Bme280Error<InterfaceE>> {
-        let mut buf = [0];
-        self.interface
-            .reg_read(self.dev_addr, CtrlMeas::START_ADDR, &mut buf)
-            .map_err(Bme280Error::Inteface)?;
-        let mut ctrl_meas = CtrlMeas(buf[0]);
-        ctrl_meas.set_mode(mode as u8);
-        self.interface
-            .reg_write(self.dev_addr, CtrlMeas::START_ADDR, ctrl_meas.0)
-            .map_err(Bme280Error::Inteface)?;
+    /// Reset sensor, then read calibration and apply current config again. Calibration is
+    /// kept if any step fails
+    pub fn soft_reset(&mut self) -> Result<(), Bme280Error<InterfaceE>> {
+        self.calib_data = self.run(SoftReset::new(self.config, self.chip, InterfaceT::SPI_3W))?;
         Ok(())
     }
 
-    fn read_calib(
-        interface: &mut InterfaceT,
-        dev_addr: u8,
-    ) -> Result<CalibData, Bme280Error<InterfaceE>> {
-        let mut calib00_25 = Calib00_25([0; Calib00_25Arr::REG_SIZE]);
-        let mut calib26_41 = Calib26_41([0; Calib26_41Arr::REG_SIZE]);
+    pub fn do_measurement(&mut self) -> Result<Measurement, Bme280Error<InterfaceE>> {
+        let raw_measures = self.run(Measure::new(&self.config))?;
+
+        Ok(self.compensator().compensate_raw(&raw_measures))
+    }
 
-        interface
-            .reg_read(dev_addr, Calib00_25Arr::START_ADDR, &mut calib00_25.0)
-            .map_err(Bme280Error::Inteface)?;
-        interface
-            .reg_read(dev_addr, Calib26_41Arr::START_ADDR, &mut calib26_41.0)
-            .map_err(Bme280Error::Inteface)?;
+    /// Same as [`Self::do_measurement`], but compensated with floating point formulas
+    #[cfg(feature = "float")]
+    pub fn do_measurement_f32(&mut self) -> Result<MeasurementF32, Bme280Error<InterfaceE>> {
+        let raw_measures = self.run(Measure::new(&self.config))?;
 
-        Ok(CalibData::new(calib00_25, calib26_41))
+        Ok(self.compensator().compensate_f32(
+            raw_measures.get_temp(),
+            raw_measures.get_press(),
+            raw_measures.get_hum(),
+        ))
     }
 
-    fn reset(
-        interface: &mut InterfaceT,
-        dev_addr: u8,
-        delay: &mut DelayT,
-    ) -> Result<(), Bme280Error<InterfaceE>> {
-        let mut reset = Reset(0);
-        reset.set_reset(Reset::RESET_BYTE);
-        interface
-            .reg_write(dev_addr, Reset::START_ADDR, reset.0)
-            .map_err(Bme280Error::Inteface)?;
-        delay.delay_ms(10);
+    /// Trigger forced conversion and return without waiting for it to finish
+    pub fn start_measurement(&mut self) -> Result<(), Bme280Error<InterfaceE>> {
+        self.run(SetMode::new(Mode::Forced))
+    }
 
-        Ok(())
+    /// Check once whether conversion triggered by [`Self::start_measurement`] is done,
+    /// and if so read it out
+    pub fn poll_measurement(&mut self) -> nb::Result<Measurement, Bme280Error<InterfaceE>> {
+        match self.run(PollMeasurement::new())? {
+            Some(raw_measures) => Ok(self.compensator().compensate_raw(&raw_measures)),
+            None => Err(nb::Error::WouldBlock),
+        }
+    }
+
+    /// Switch sensor to normal mode, in which it cycles between conversions and
+    /// standby periods on its own
+    pub fn start_normal(&mut self) -> Result<(), Bme280Error<InterfaceE>> {
+        self.run(SetMode::new(Mode::Normal))
+    }
+
+    /// Read the most recent conversion without triggering a new one
+    pub fn read_latest(&mut self) -> Result<Measurement, Bme280Error<InterfaceE>> {
+        let raw_measures = self.run(ReadRaw::new())?;
+
+        Ok(self.compensator().compensate_raw(&raw_measures))
+    }
+
+    pub fn get_calib(&self) -> &CalibData {
+        &self.calib_data
+    }
+
+    pub fn chip(&self) -> ChipVariant {
+        self.chip
     }
 
-    fn read_id(interface: &mut InterfaceT, dev_addr: u8) -> Result<u8, Bme280Error<InterfaceE>> {
-        let mut buf = [0];
-        interface
-            .reg_read(dev_addr, Id::START_ADDR, &mut buf)
-            .map_err(Bme280Error::Inteface)?;
+    /// Compensator matching this sensor, e.g. to process logged raw readings later
+    pub fn compensator(&self) -> Compensator {
+        Compensator {
+            calib_data: self.calib_data,
+            chip: self.chip,
+            trim: self.config.trim,
+        }
+    }
+}
+
+impl<InterfaceT, DelayT> Bme280<InterfaceT, DelayT> {
+    /// Give back interface and delay. Sensor keeps its current mode
+    pub fn free(self) -> (InterfaceT, DelayT) {
+        (self.interface, self.delay)
+    }
 
-        Ok(Id(buf[0]).get_id())
+    /// Give back interface and delay, keeping driver state to continue with later
+    pub fn detach(self) -> (DetachedBme280, InterfaceT, DelayT) {
+        let detached = DetachedBme280 {
+            dev_addr: self.dev_addr,
+            chip: self.chip,
+            calib_data: self.calib_data,
+            config: self.config,
+        };
+        (detached, self.interface, self.delay)
     }
+}
 
-    pub fn do_measurement(&mut self) -> Result<(I22F10, I24F8, I22F10), Bme280Error<InterfaceE>> {
-        // Set mode to forced
-        self.set_mode(Mode::Forced)?;
+impl DetachedBme280 {
+    /// Same as [`Bme280::init`], but interface and delay are only borrowed for the call
+    pub fn init<InterfaceT, InterfaceE, DelayT>(
+        interface: &mut InterfaceT,
+        dev_addr: impl Into<u8>,
+        delay: &mut DelayT,
+        config: Bme280Config,
+    ) -> Result<Self, Bme280Error<InterfaceE>>
+    where
+        InterfaceT: RegRead<Error = InterfaceE> + RegWrite<Error = InterfaceE>,
+        DelayT: DelayMs<u16>,
+    {
+        let bme280 = Bme280::init(Borrowed(interface), dev_addr, Borrowed(delay), config)?;
+        Ok(bme280.detach().0)
+    }
 
-        let mut measuring = true;
-        while measuring {
-            let mut buf = [0];
-            self.interface
-                .reg_read(self.dev_addr, Status::START_ADDR, &mut buf)
-                .map_err(Bme280Error::Inteface)?;
-            let status = Status(buf[0]);
-            measuring = status.get_measuring() == 1;
+    /// Run `f` with driver using borrowed interface and delay. State changes made by `f`
+    /// (e.g. [`Bme280::reconfigure`]) are kept
+    pub fn with<'a, InterfaceT, InterfaceE, DelayT, R>(
+        &mut self,
+        interface: &'a mut InterfaceT,
+        delay: &'a mut DelayT,
+        f: impl FnOnce(&mut Bme280<Borrowed<'a, InterfaceT>, Borrowed<'a, DelayT>>) -> R,
+    ) -> R
+    where
+        InterfaceT: RegRead<Error = InterfaceE> + RegWrite<Error = InterfaceE>,
+        DelayT: DelayMs<u16>,
+    {
+        let mut bme280 = self.attach(Borrowed(interface), Borrowed(delay));
+        let res = f(&mut bme280);
+        *self = bme280.detach().0;
+        res
+    }
 
-            self.delay.delay_ms(10);
+    /// Hand interface and delay to the driver again
+    pub fn attach<InterfaceT, DelayT>(
+        self,
+        interface: InterfaceT,
+        delay: DelayT,
+    ) -> Bme280<InterfaceT, DelayT> {
+        Bme280 {
+            interface,
+            dev_addr: self.dev_addr,
+            chip: self.chip,
+            calib_data: self.calib_data,
+            delay,
+            config: self.config,
         }
+    }
+}
+
+/// Turns raw ADC readings into compensated measurements, without access to the sensor
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Compensator {
+    pub calib_data: CalibData,
+    pub chip: ChipVariant,
+    pub trim: Trim,
+}
+
+// Compensation formulas in one number format, along with conversion of trim into it
+pub(crate) trait Formulas {
+    type TFine: Copy + core::ops::Add<Output = Self::TFine>;
+    type Temperature;
+    type Pressure: core::ops::Add<Output = Self::Pressure>;
+    type Humidity: Copy
+        + core::ops::Add<Output = Self::Humidity>
+        + core::ops::Mul<Output = Self::Humidity>
+        + PartialOrd;
+
+    fn t_fine(&self, adc_t: u32) -> Self::TFine;
+    fn temperature(t_fine: Self::TFine) -> Self::Temperature;
+    fn pressure(&self, t_fine: Self::TFine, adc_p: u32) -> Self::Pressure;
+    fn humidity(&self, t_fine: Self::TFine, adc_h: u32) -> Self::Humidity;
 
-        // Read out measures
-        let mut buf = [0; RawMeasures::REG_SIZE];
-        self.interface
-            .reg_read(self.dev_addr, RawMeasures::START_ADDR, &mut buf)
-            .map_err(Bme280Error::Inteface)?;
-        let raw_measures = RawMeasures(buf);
+    /// Temperature in °C as `t_fine` difference
+    fn t_fine_offset(temperature: I22F10) -> Self::TFine;
+    fn pressure_value(pressure: I24F8) -> Self::Pressure;
+    fn humidity_value(humidity: I22F10) -> Self::Humidity;
+}
+
+/// Compensated channels, `None` for skipped ones and ones compensated with skipped temperature
+pub(crate) struct Channels<F: Formulas> {
+    pub(crate) t_fine: F::TFine,
+    pub(crate) temperature: Option<F::Temperature>,
+    pub(crate) pressure: Option<F::Pressure>,
+    pub(crate) humidity: Option<F::Humidity>,
+}
+
+struct FixedFormulas<'a>(&'a Compensator);
 
-        let (t_fine, temp) = Self::compensate_t(&self.calib_data, raw_measures.get_temp());
-        let pres = Self::compensate_p(&self.calib_data, t_fine, raw_measures.get_press());
-        let hum = Self::compensate_h(&self.calib_data, t_fine, raw_measures.get_hum());
+impl Formulas for FixedFormulas<'_> {
+    type TFine = i32;
+    type Temperature = I22F10;
+    type Pressure = I24F8;
+    type Humidity = I22F10;
 
+    fn t_fine(&self, adc_t: u32) -> i32 {
+        self.0.compensate_t(adc_t).0
+    }
+
+    fn temperature(t_fine: i32) -> I22F10 {
         // Scale temp
-        let temp = I22F10::new(temp, 0) / I22F10::new(100, 0);
+        I22F10::new(Compensator::t_fine_to_temp(t_fine), 0) / I22F10::new(100, 0)
+    }
+
+    fn pressure(&self, t_fine: i32, adc_p: u32) -> I24F8 {
+        if cfg!(feature = "pressure-i32") {
+            I24F8::new(self.0.compensate_p_32(t_fine, adc_p) as i32, 0)
+        } else {
+            self.0.compensate_p(t_fine, adc_p)
+        }
+    }
 
-        Ok((temp, pres, hum))
+    fn humidity(&self, t_fine: i32, adc_h: u32) -> I22F10 {
+        self.0.compensate_h(t_fine, adc_h)
     }
 
-    pub fn get_calib(&self) -> &CalibData {
-        &self.calib_data
+    // 1 °C is 5120 LSB of t_fine, and 1024 LSB of I22F10
+    fn t_fine_offset(temperature: I22F10) -> i32 {
+        temperature.to_bits() * 5
+    }
+
+    fn pressure_value(pressure: I24F8) -> I24F8 {
+        pressure
+    }
+
+    fn humidity_value(humidity: I22F10) -> I22F10 {
+        humidity
     }
 }
 
 // From BME 280 datasheet page 25
-impl<T1, T2> Bme280<T1, T2> {
-    fn compensate_t(calib_data: &CalibData, adc_t: u32) -> (i32, i32) {
+impl Compensator {
+    /// Compensator for BME280 without trim
+    pub fn new(calib_data: CalibData) -> Self {
+        Self {
+            calib_data,
+            chip: ChipVariant::Bme280,
+            trim: Trim::default(),
+        }
+    }
+
+    /// Compensates `raw` `(adc_t, adc_p, adc_h)` readings into `out`, up to the shorter of
+    /// them. Returns number of written measurements
+    pub fn compensate_slice(&self, raw: &[(u32, u32, u32)], out: &mut [Measurement]) -> usize {
+        let mut written = 0;
+        for (measurement, &(adc_t, adc_p, adc_h)) in out.iter_mut().zip(raw) {
+            *measurement = self.compensate(adc_t, adc_p, adc_h);
+            written += 1;
+        }
+        written
+    }
+
+    /// Lazily compensates `raw` `(adc_t, adc_p, adc_h)` readings
+    pub fn compensate_iter<'a>(
+        &'a self,
+        raw: &'a [(u32, u32, u32)],
+    ) -> impl Iterator<Item = Measurement> + 'a {
+        raw.iter()
+            .map(move |&(adc_t, adc_p, adc_h)| self.compensate(adc_t, adc_p, adc_h))
+    }
+
+    pub(crate) fn compensate_raw(
+        &self,
+        raw_measures: &RawMeasures<[u8; RawMeasures::REG_SIZE]>,
+    ) -> Measurement {
+        self.compensate(
+            raw_measures.get_temp(),
+            raw_measures.get_press(),
+            raw_measures.get_hum(),
+        )
+    }
+
+    /// Skipped measurements (reported as `0x80000`, or `0x8000` for humidity) are `None`
+    pub fn compensate(&self, adc_t: u32, adc_p: u32, adc_h: u32) -> Measurement {
+        let channels = self.compensate_with(FixedFormulas(self), adc_t, adc_p, adc_h);
+
+        Measurement {
+            temperature: channels.temperature,
+            pressure: channels.pressure,
+            humidity: channels.humidity,
+            adc_t,
+            adc_p,
+            adc_h,
+            t_fine: channels.t_fine,
+        }
+    }
+
+    // Decides which channels are valid and applies trim, for all number formats
+    pub(crate) fn compensate_with<F: Formulas>(
+        &self,
+        formulas: F,
+        adc_t: u32,
+        adc_p: u32,
+        adc_h: u32,
+    ) -> Channels<F> {
+        let trim = &self.trim;
+
+        let t_fine = formulas.t_fine(adc_t) + F::t_fine_offset(trim.temperature_offset);
+        // Pressure and humidity are compensated with t_fine, which is garbage when
+        // temperature is skipped
+        let temp_measured = adc_t != RawMeasures::SKIPPED_TEMP_PRESS;
+        let pres_measured = temp_measured && adc_p != RawMeasures::SKIPPED_TEMP_PRESS;
+        let hum_measured =
+            temp_measured && self.chip.has_humidity() && adc_h != RawMeasures::SKIPPED_HUM;
+
+        let pressure = pres_measured
+            .then(|| formulas.pressure(t_fine, adc_p) + F::pressure_value(trim.pressure_offset));
+        let humidity = hum_measured.then(|| {
+            let hum = formulas.humidity(t_fine, adc_h) * F::humidity_value(trim.humidity_gain)
+                + F::humidity_value(trim.humidity_offset);
+            let (min, max) = (
+                F::humidity_value(I22F10::new(0, 0)),
+                F::humidity_value(I22F10::new(100, 0)),
+            );
+            if hum < min {
+                min
+            } else if hum > max {
+                max
+            } else {
+                hum
+            }
+        });
+
+        Channels {
+            t_fine,
+            temperature: temp_measured.then(|| F::temperature(t_fine)),
+            pressure,
+            humidity,
+        }
+    }
+
+    pub fn compensate_t(&self, adc_t: u32) -> (i32, i32) {
+        let calib_data = &self.calib_data;
         let var1 = (((adc_t >> 3) as i32 - ((calib_data.dig_t1 as i32) << 1))
             * (calib_data.dig_t2 as i32))
             >> 11;
@@ -242,12 +976,17 @@ impl<T1, T2> Bme280<T1, T2> {
             * (calib_data.dig_t3 as i32))
             >> 14;
         let t_fine = var1 + var2;
-        let t = (t_fine * 5 + 128) >> 8;
 
-        (t_fine, t)
+        (t_fine, Self::t_fine_to_temp(t_fine))
+    }
+
+    // Temperature in 0.01 °C
+    fn t_fine_to_temp(t_fine: i32) -> i32 {
+        (t_fine * 5 + 128) >> 8
     }
 
-    fn compensate_p(calib_data: &CalibData, t_fine: i32, adc_p: u32) -> I24F8 {
+    pub fn compensate_p(&self, t_fine: i32, adc_p: u32) -> I24F8 {
+        let calib_data = &self.calib_data;
         let mut var1 = (t_fine as i64) - 128000;
         let mut var2 = var1 * var1 * calib_data.dig_p6 as i64;
         var2 += (var1 * calib_data.dig_p5 as i64) << 17;
@@ -267,7 +1006,48 @@ impl<T1, T2> Bme280<T1, T2> {
         I24F8(p as u32)
     }
 
-    fn compensate_h(calib_data: &CalibData, t_fine: i32, adc_h: u32) -> I22F10 {
+    // 32-bit variant from BMP 280 datasheet page 22, returns pressure in Pa with 1 Pa resolution.
+    // Divisions truncate towards zero as in Bosch reference driver, which is what datasheet
+    // example values are computed with. Wrapping ops mirror C on out of range ADC values
+    pub fn compensate_p_32(&self, t_fine: i32, adc_p: u32) -> u32 {
+        let calib_data = &self.calib_data;
+        let mut var1 = (t_fine >> 1).wrapping_sub(64000);
+        let var1_sq = (var1 >> 2).wrapping_mul(var1 >> 2);
+        let mut var2 = (var1_sq >> 11).wrapping_mul(calib_data.dig_p6 as i32);
+        var2 = var2.wrapping_add(var1.wrapping_mul(calib_data.dig_p5 as i32) << 1);
+        var2 = (var2 >> 2).wrapping_add((calib_data.dig_p4 as i32) << 16);
+        var1 = ((calib_data.dig_p3 as i32).wrapping_mul(var1_sq >> 13) / 8)
+            .wrapping_add((calib_data.dig_p2 as i32).wrapping_mul(var1) / 2)
+            / 262144;
+        var1 = 32768i32
+            .wrapping_add(var1)
+            .wrapping_mul(calib_data.dig_p1 as i32)
+            / 32768;
+        if var1 == 0 {
+            return 0; // avoid exception caused by division by zero
+        }
+        let mut p = (1048576i32
+            .wrapping_sub(adc_p as i32)
+            .wrapping_sub(var2 >> 12) as u32)
+            .wrapping_mul(3125);
+        if p < 0x80000000 {
+            p = (p << 1) / var1 as u32;
+        } else {
+            p = (p / var1 as u32).wrapping_mul(2);
+        }
+        var1 = (calib_data.dig_p9 as i32)
+            .wrapping_mul((((p >> 3).wrapping_mul(p >> 3)) >> 13) as i32)
+            >> 12;
+        var2 = ((p >> 2) as i32).wrapping_mul(calib_data.dig_p8 as i32) >> 13;
+
+        let correction = var1
+            .wrapping_add(var2)
+            .wrapping_add(calib_data.dig_p7 as i32);
+        (p as i32).wrapping_add(correction >> 4) as u32
+    }
+
+    pub fn compensate_h(&self, t_fine: i32, adc_h: u32) -> I22F10 {
+        let calib_data = &self.calib_data;
         let mut val = t_fine - 76800;
         val = ((((adc_h << 14) as i32
             - ((calib_data.dig_h4 as i32) << 20)
@@ -294,25 +1074,7 @@ bitfield! {
     pub u32, get_frac, set_frac: 9, 0;
     pub i32, get_int, set_int: 31, 10;
 }
-
-impl core::ops::Div for I22F10 {
-    type Output = Self;
-
-    fn div(self, rhs: Self) -> Self::Output {
-        let lsh = self.0 as u64;
-        let rhs = rhs.0 as u64;
-        Self((lsh * (1 << 10) / rhs) as u32)
-    }
-}
-
-impl I22F10 {
-    pub fn new(int: i32, frac: u32) -> Self {
-        let mut num = Self(0);
-        num.set_int(int);
-        num.set_frac(frac);
-        num
-    }
-}
+fixed_point!(I22F10, 10);
 
 bitfield! {
     #[derive(PartialEq, Eq, Debug, Clone, Copy)]
@@ -320,35 +1082,167 @@ bitfield! {
     pub u32, get_frac, set_frac: 7, 0;
     pub i32, get_int, set_int: 31, 8;
 }
+fixed_point!(I24F8, 8);
 
-impl core::ops::Div for I24F8 {
-    type Output = Self;
+// Signed arithmetic on fixed point numbers, stored as two's complement in u32.
+// Operators wrap on overflow, checked_* and saturating_* variants detect or clamp it
+macro_rules! fixed_point {
+    ($name:ident, $frac_bits:literal) => {
+        impl $name {
+            pub const FRAC_BITS: u32 = $frac_bits;
 
-    fn div(self, rhs: Self) -> Self::Output {
-        let lsh = self.0 as u64;
-        let rhs = rhs.0 as u64;
-        Self((lsh * (1 << 8) / rhs) as u32)
-    }
-}
+            pub fn new(int: i32, frac: u32) -> Self {
+                let mut num = Self(0);
+                num.set_int(int);
+                num.set_frac(frac);
+                num
+            }
 
-impl I24F8 {
-    pub fn new(int: i32, frac: u32) -> Self {
-        let mut num = Self(0);
-        num.set_int(int);
-        num.set_frac(frac);
-        num
-    }
-}
+            /// Build from raw value, scaled by `2^FRAC_BITS`
+            pub const fn from_bits(bits: i32) -> Self {
+                Self(bits as u32)
+            }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    const MOCK_CALIB_DATA: &CalibData = &CalibData {
-        dig_t1: 28765,
-        dig_t2: 27210,
-        dig_t3: 50,
-        dig_p1: 37234,
-        dig_p2: -10553,
+            /// Raw value, scaled by `2^FRAC_BITS`
+            pub const fn to_bits(self) -> i32 {
+                self.0 as i32
+            }
+
+            pub fn to_f32(self) -> f32 {
+                self.to_bits() as f32 / (1 << $frac_bits) as f32
+            }
+
+            /// Value multiplied by 1000, truncated towards zero
+            pub fn to_milli(self) -> i64 {
+                self.to_bits() as i64 * 1000 / (1 << $frac_bits)
+            }
+
+            pub fn checked_add(self, rhs: Self) -> Option<Self> {
+                self.to_bits()
+                    .checked_add(rhs.to_bits())
+                    .map(Self::from_bits)
+            }
+
+            pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+                self.to_bits()
+                    .checked_sub(rhs.to_bits())
+                    .map(Self::from_bits)
+            }
+
+            pub fn checked_mul(self, rhs: Self) -> Option<Self> {
+                i32::try_from(Self::mul_bits(self, rhs))
+                    .ok()
+                    .map(Self::from_bits)
+            }
+
+            pub fn checked_div(self, rhs: Self) -> Option<Self> {
+                if rhs.to_bits() == 0 {
+                    return None;
+                }
+                i32::try_from(Self::div_bits(self, rhs))
+                    .ok()
+                    .map(Self::from_bits)
+            }
+
+            pub fn checked_neg(self) -> Option<Self> {
+                self.to_bits().checked_neg().map(Self::from_bits)
+            }
+
+            pub fn saturating_add(self, rhs: Self) -> Self {
+                Self::from_bits(self.to_bits().saturating_add(rhs.to_bits()))
+            }
+
+            pub fn saturating_sub(self, rhs: Self) -> Self {
+                Self::from_bits(self.to_bits().saturating_sub(rhs.to_bits()))
+            }
+
+            pub fn saturating_mul(self, rhs: Self) -> Self {
+                Self::from_bits(
+                    Self::mul_bits(self, rhs).clamp(i32::MIN as i64, i32::MAX as i64) as i32,
+                )
+            }
+
+            /// Panics if `rhs` is zero, same as integer division
+            pub fn saturating_div(self, rhs: Self) -> Self {
+                Self::from_bits(
+                    Self::div_bits(self, rhs).clamp(i32::MIN as i64, i32::MAX as i64) as i32,
+                )
+            }
+
+            fn mul_bits(lhs: Self, rhs: Self) -> i64 {
+                (lhs.to_bits() as i64 * rhs.to_bits() as i64) >> $frac_bits
+            }
+
+            fn div_bits(lhs: Self, rhs: Self) -> i64 {
+                ((lhs.to_bits() as i64) << $frac_bits) / rhs.to_bits() as i64
+            }
+        }
+
+        impl core::ops::Add for $name {
+            type Output = Self;
+
+            fn add(self, rhs: Self) -> Self::Output {
+                Self::from_bits(self.to_bits().wrapping_add(rhs.to_bits()))
+            }
+        }
+
+        impl core::ops::Sub for $name {
+            type Output = Self;
+
+            fn sub(self, rhs: Self) -> Self::Output {
+                Self::from_bits(self.to_bits().wrapping_sub(rhs.to_bits()))
+            }
+        }
+
+        impl core::ops::Mul for $name {
+            type Output = Self;
+
+            fn mul(self, rhs: Self) -> Self::Output {
+                Self::from_bits(Self::mul_bits(self, rhs) as i32)
+            }
+        }
+
+        impl core::ops::Div for $name {
+            type Output = Self;
+
+            fn div(self, rhs: Self) -> Self::Output {
+                Self::from_bits(Self::div_bits(self, rhs) as i32)
+            }
+        }
+
+        impl core::ops::Neg for $name {
+            type Output = Self;
+
+            fn neg(self) -> Self::Output {
+                Self::from_bits(self.to_bits().wrapping_neg())
+            }
+        }
+
+        impl PartialOrd for $name {
+            fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for $name {
+            fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+                self.to_bits().cmp(&other.to_bits())
+            }
+        }
+    };
+}
+use fixed_point;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::submodules::registers::{RegAddr, Status};
+    pub(crate) const MOCK_CALIB_DATA: &CalibData = &CalibData {
+        dig_t1: 28765,
+        dig_t2: 27210,
+        dig_t3: 50,
+        dig_p1: 37234,
+        dig_p2: -10553,
         dig_p3: 3024,
         dig_p4: 7263,
         dig_p5: 31,
@@ -383,17 +1277,436 @@ mod tests {
     impl RegWrite for DummyInterface {
         type Error = ();
 
-        fn reg_write(&mut self, dev_addr: u8, reg_addr: u8, data: u8) -> Result<(), Self::Error> {
+        fn reg_write(
+            &mut self,
+            _dev_addr: u8,
+            _reg_addr: u8,
+            _data: u8,
+        ) -> Result<(), Self::Error> {
             unimplemented!()
         }
     }
 
     impl DelayMs<u16> for DummyDelay {
-        fn delay_ms(&mut self, ms: u16) {
+        fn delay_ms(&mut self, _ms: u16) {
             unimplemented!()
         }
     }
 
+    // Register file of a sensor with MOCK_CALIB_DATA calibration, holding a single
+    // conversion result
+    pub(crate) fn mock_regs() -> [u8; 256] {
+        let mut regs = [0; 256];
+        regs[0x88..=0xA1].copy_from_slice(&[
+            0x5D, 0x70, 0x4A, 0x6A, 0x32, 0x00, 0x72, 0x91, 0xC7, 0xD6, 0xD0, 0x0B, 0x5F, 0x1C,
+            0x1F, 0x00, 0xF9, 0xFF, 0xAC, 0x26, 0x0A, 0xD8, 0xBD, 0x10, 0x00, 0x4B,
+        ]);
+        regs[0xE1..=0xF0].copy_from_slice(&[
+            0x75, 0x01, 0x00, 0x12, 0x25, 0x03, 0x1E, 0x42, 0x41, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+            0xFF, 0xFF,
+        ]);
+        regs[0xD0] = Id::BME280_STANDARD_ID;
+        // press = 322858, temp = 526514, hum = 23549
+        regs[0xF7..=0xFE].copy_from_slice(&[0x4E, 0xD2, 0xA0, 0x80, 0x8B, 0x20, 0x5B, 0xFD]);
+        regs
+    }
+
+    struct MockInterface {
+        regs: [u8; 256],
+        // Addresses of control registers written since last reset of the log
+        writes: [u8; 16],
+        writes_len: usize,
+        // Register, writes to which are ignored
+        read_only: Option<u8>,
+        // Number of status reads reporting NVM copy in progress
+        nvm_copy_reads: usize,
+    }
+
+    impl MockInterface {
+        fn new() -> Self {
+            Self {
+                regs: mock_regs(),
+                writes: [0; 16],
+                writes_len: 0,
+                read_only: None,
+                nvm_copy_reads: 0,
+            }
+        }
+
+        fn writes(&self) -> &[u8] {
+            &self.writes[..self.writes_len]
+        }
+    }
+
+    impl RegRead for MockInterface {
+        type Error = ();
+
+        fn reg_read(
+            &mut self,
+            _dev_addr: u8,
+            reg_addr: u8,
+            buf: &mut [u8],
+        ) -> Result<(), Self::Error> {
+            let start = reg_addr as usize;
+            buf.copy_from_slice(&self.regs[start..start + buf.len()]);
+            if reg_addr == Status::START_ADDR && self.nvm_copy_reads > 0 {
+                self.nvm_copy_reads -= 1;
+                buf[0] |= 0b00000001;
+            }
+            Ok(())
+        }
+    }
+
+    impl RegWrite for MockInterface {
+        type Error = ();
+
+        fn reg_write(&mut self, _dev_addr: u8, reg_addr: u8, data: u8) -> Result<(), Self::Error> {
+            if self.writes_len < self.writes.len() {
+                self.writes[self.writes_len] = reg_addr;
+                self.writes_len += 1;
+            }
+            if self.read_only != Some(reg_addr) {
+                self.regs[reg_addr as usize] = data;
+            }
+            Ok(())
+        }
+    }
+
+    pub(crate) struct NoopDelay {}
+
+    impl DelayMs<u16> for NoopDelay {
+        fn delay_ms(&mut self, _ms: u16) {}
+    }
+
+    fn mock_calib_bytes() -> [u8; CalibData::BYTES_LEN] {
+        let regs = mock_regs();
+        let mut bytes = [0; CalibData::BYTES_LEN];
+        bytes[..26].copy_from_slice(&regs[0x88..=0xA1]);
+        bytes[26..].copy_from_slice(&regs[0xE1..=0xF0]);
+        bytes
+    }
+
+    #[test]
+    fn test_calib_bytes() {
+        let bytes = mock_calib_bytes();
+        assert_eq!(*MOCK_CALIB_DATA, CalibData::from_bytes(&bytes));
+
+        // Reserved register bits are not kept
+        let serialized = MOCK_CALIB_DATA.to_bytes();
+        assert_eq!(bytes[..33], serialized[..33]);
+        assert_eq!([0; 9], serialized[33..]);
+
+        let calib_data = CalibData {
+            dig_h4: -1000,
+            dig_h5: -3,
+            dig_h6: -30,
+            ..*MOCK_CALIB_DATA
+        };
+        assert_eq!(calib_data, CalibData::from_bytes(&calib_data.to_bytes()));
+    }
+
+    #[test]
+    fn test_calib_validation() {
+        assert!(MOCK_CALIB_DATA.is_valid(ChipVariant::Bme280));
+        for chip in [ChipVariant::Bme280, ChipVariant::Bmp280] {
+            assert!(!CalibData::from_bytes(&[0x00; CalibData::BYTES_LEN]).is_valid(chip));
+            assert!(!CalibData::from_bytes(&[0xFF; CalibData::BYTES_LEN]).is_valid(chip));
+        }
+        for calib_data in [
+            CalibData {
+                dig_p1: 0,
+                ..*MOCK_CALIB_DATA
+            },
+            CalibData {
+                dig_t1: 0,
+                ..*MOCK_CALIB_DATA
+            },
+            // Corrupted words, not caught as blank block
+            CalibData {
+                dig_t1: 60000,
+                ..*MOCK_CALIB_DATA
+            },
+            CalibData {
+                dig_p1: 1000,
+                ..*MOCK_CALIB_DATA
+            },
+            CalibData {
+                dig_t2: -MOCK_CALIB_DATA.dig_t2,
+                ..*MOCK_CALIB_DATA
+            },
+            CalibData {
+                dig_p2: -MOCK_CALIB_DATA.dig_p2,
+                ..*MOCK_CALIB_DATA
+            },
+        ] {
+            assert!(!calib_data.is_valid(ChipVariant::Bme280));
+            assert!(!calib_data.is_valid(ChipVariant::Bmp280));
+        }
+        // BMP280 datasheet example calibration
+        let calib_data = CalibData {
+            dig_t1: 27504,
+            dig_t2: 26435,
+            dig_p1: 36477,
+            dig_p2: -10685,
+            ..*MOCK_CALIB_DATA
+        };
+        assert!(calib_data.is_valid(ChipVariant::Bmp280));
+
+        // Humidity block is only required on BME280
+        let mut bytes = MOCK_CALIB_DATA.to_bytes();
+        bytes[Calib00_25Arr::REG_SIZE..].fill(0xFF);
+        let calib_data = CalibData::from_bytes(&bytes);
+        assert!(!calib_data.is_valid(ChipVariant::Bme280));
+        assert!(calib_data.is_valid(ChipVariant::Bmp280));
+        let calib_data = CalibData {
+            dig_h2: 0,
+            dig_h3: 0,
+            dig_h4: 0,
+            dig_h5: 0,
+            dig_h6: 0,
+            ..*MOCK_CALIB_DATA
+        };
+        assert!(!calib_data.is_valid(ChipVariant::Bme280));
+        assert!(calib_data.is_valid(ChipVariant::Bmp280));
+
+        // Loose connector
+        let mut interface = MockInterface::new();
+        interface.regs[0x88..=0xA1].fill(0xFF);
+        assert!(matches!(
+            Bme280::<_, NoopDelay>::read_calibration(&mut interface, 0x76),
+            Err(Bme280Error::InvalidCalibration)
+        ));
+        interface.regs = mock_regs();
+        interface.regs[0xE1..=0xF0].fill(0xFF);
+        assert!(matches!(
+            Bme280::<_, NoopDelay>::read_calibration(&mut interface, 0x76),
+            Err(Bme280Error::InvalidCalibration)
+        ));
+        // Retry after contact is restored
+        interface.regs = mock_regs();
+        let calib_data = Bme280::<_, NoopDelay>::read_calibration(&mut interface, 0x76).unwrap();
+        assert_eq!(*MOCK_CALIB_DATA, calib_data);
+
+        let config = Bme280Config {
+            temp_oversampling: Oversampling::X1,
+            ..Default::default()
+        };
+        let mut bme280 = Bme280::init(interface, 0x76, NoopDelay {}, config).unwrap();
+        bme280.interface.regs[0x88..=0xA1].fill(0x00);
+        assert!(matches!(
+            bme280.reload_calib(),
+            Err(Bme280Error::InvalidCalibration)
+        ));
+        assert_eq!(MOCK_CALIB_DATA, bme280.get_calib());
+
+        let mut interface = MockInterface::new();
+        interface.regs[0x88..=0xA1].fill(0xFF);
+        assert!(matches!(
+            Bme280::init(interface, 0x76, NoopDelay {}, Bme280Config::default()),
+            Err(Bme280Error::InvalidCalibration)
+        ));
+        assert!(matches!(
+            Bme280::init_with_calib(
+                MockInterface::new(),
+                0x76,
+                NoopDelay {},
+                Bme280Config::default(),
+                CalibData::from_bytes(&[0xFF; CalibData::BYTES_LEN])
+            ),
+            Err(Bme280Error::InvalidCalibration)
+        ));
+    }
+
+    #[test]
+    fn test_init_with_calib() {
+        let mut interface = MockInterface::new();
+        // Calibration is not read from the sensor
+        interface.regs[0x88..=0xA1].fill(0);
+        interface.regs[0xE1..=0xF0].fill(0);
+        let config = Bme280Config {
+            temp_oversampling: Oversampling::X1,
+            ..Default::default()
+        };
+
+        let calib_data = CalibData::from_bytes(&mock_calib_bytes());
+        let mut bme280 =
+            Bme280::init_with_calib(interface, 0x76, NoopDelay {}, config, calib_data).unwrap();
+        assert_eq!(MOCK_CALIB_DATA, bme280.get_calib());
+        let measurement = bme280.do_measurement().unwrap();
+        assert_eq!(21, measurement.temperature.unwrap().get_int());
+    }
+
+    #[test]
+    fn test_compensate() {
+        let raw_measures = RawMeasures(mock_regs()[0xF7..=0xFE].try_into().unwrap());
+        let measurement = Compensator::new(*MOCK_CALIB_DATA).compensate_raw(&raw_measures);
+
+        assert_eq!(526514, measurement.adc_t);
+        assert_eq!(322858, measurement.adc_p);
+        assert_eq!(23549, measurement.adc_h);
+        assert_eq!(110074, measurement.t_fine);
+        assert_eq!(21, measurement.temperature.unwrap().get_int());
+        assert!(measurement.humidity.is_some());
+    }
+
+    #[test]
+    fn test_compensator_batch() {
+        let compensator = Compensator::new(*MOCK_CALIB_DATA);
+        let raw = [
+            (526514, 322858, 23549),
+            (400000, 322858, 23549),
+            (
+                526514,
+                RawMeasures::SKIPPED_TEMP_PRESS,
+                RawMeasures::SKIPPED_HUM,
+            ),
+        ];
+        let expected = raw.map(|(adc_t, adc_p, adc_h)| compensator.compensate(adc_t, adc_p, adc_h));
+        assert_eq!(21, expected[0].temperature.unwrap().get_int());
+        assert_eq!(None, expected[2].pressure);
+
+        let mut out = [expected[2]; 4];
+        assert_eq!(3, compensator.compensate_slice(&raw, &mut out));
+        assert_eq!(expected, out[..3]);
+        let mut out = [expected[2]; 2];
+        assert_eq!(2, compensator.compensate_slice(&raw, &mut out));
+        assert_eq!(expected[..2], out);
+
+        assert!(compensator.compensate_iter(&raw).eq(expected));
+
+        let compensator = Compensator {
+            chip: ChipVariant::Bmp280,
+            ..compensator
+        };
+        assert_eq!(None, compensator.compensate(526514, 322858, 23549).humidity);
+    }
+
+    #[test]
+    fn test_compensate_trim() {
+        let raw_measures = RawMeasures(mock_regs()[0xF7..=0xFE].try_into().unwrap());
+        let trim = Trim {
+            temperature_offset: I22F10::new(-2, 0),
+            pressure_offset: I24F8::new(-50, 0),
+            humidity_gain: I22F10::new(1, 512),
+            humidity_offset: I22F10::new(3, 0),
+        };
+        let measurement = Compensator {
+            trim,
+            ..Compensator::new(*MOCK_CALIB_DATA)
+        }
+        .compensate_raw(&raw_measures);
+
+        let t_fine = 110074 - 2 * 5120;
+        assert_eq!(t_fine, measurement.t_fine);
+        assert_eq!(1950, measurement.temperature.unwrap().to_milli() / 10);
+        // Pressure and humidity are compensated with corrected temperature
+        let pressure = if cfg!(feature = "pressure-i32") {
+            let pressure = Compensator::new(*MOCK_CALIB_DATA).compensate_p_32(t_fine, 322858);
+            I24F8::new(pressure as i32, 0)
+        } else {
+            Compensator::new(*MOCK_CALIB_DATA).compensate_p(t_fine, 322858)
+        };
+        assert_eq!(Some(pressure - I24F8::new(50, 0)), measurement.pressure);
+        let humidity = Compensator::new(*MOCK_CALIB_DATA).compensate_h(t_fine, 23549);
+        assert_eq!(
+            Some(humidity * I22F10::new(1, 512) + I22F10::new(3, 0)),
+            measurement.humidity
+        );
+
+        // Humidity stays within 0..100 %
+        let trim = Trim {
+            humidity_gain: I22F10::new(10, 0),
+            ..Default::default()
+        };
+        let measurement = Compensator {
+            trim,
+            ..Compensator::new(*MOCK_CALIB_DATA)
+        }
+        .compensate_raw(&raw_measures);
+        assert_eq!(Some(I22F10::new(100, 0)), measurement.humidity);
+    }
+
+    #[test]
+    fn test_two_point_trim() {
+        // Sensor reads 30 % at 33 % reference salt and 70 % at 75 %
+        let (gain, offset) = Trim::two_point(
+            (I22F10::new(30, 0), I22F10::new(70, 0)),
+            (I22F10::new(33, 0), I22F10::new(75, 0)),
+        )
+        .unwrap();
+        assert_eq!(I22F10::new(1, 51), gain);
+        // Gain and offset are quantized to 1/1024
+        let low = (I22F10::new(30, 0) * gain + offset).to_milli();
+        assert!((low - 33000).abs() < 50, "{low}");
+        let high = (I22F10::new(70, 0) * gain + offset).to_milli();
+        assert!((high - 75000).abs() < 50, "{high}");
+
+        assert_eq!(
+            None,
+            Trim::two_point(
+                (I22F10::new(30, 0), I22F10::new(30, 0)),
+                (I22F10::new(33, 0), I22F10::new(75, 0)),
+            )
+        );
+    }
+
+    #[test]
+    fn test_compensate_skipped() {
+        let mut regs = mock_regs();
+        regs[0xF7..=0xF9].copy_from_slice(&[0x80, 0x00, 0x00]);
+        regs[0xFD..=0xFE].copy_from_slice(&[0x80, 0x00]);
+        let raw_measures = RawMeasures(regs[0xF7..=0xFE].try_into().unwrap());
+        let measurement = Compensator::new(*MOCK_CALIB_DATA).compensate_raw(&raw_measures);
+
+        assert_eq!(21, measurement.temperature.unwrap().get_int());
+        assert_eq!(None, measurement.pressure);
+        assert_eq!(None, measurement.humidity);
+
+        regs[0xFA..=0xFC].copy_from_slice(&[0x80, 0x00, 0x00]);
+        let raw_measures = RawMeasures(regs[0xF7..=0xFE].try_into().unwrap());
+        let measurement = Compensator::new(*MOCK_CALIB_DATA).compensate_raw(&raw_measures);
+        assert_eq!(None, measurement.temperature);
+
+        // Pressure and humidity can't be compensated without temperature
+        let measurement = Compensator::new(*MOCK_CALIB_DATA).compensate(
+            RawMeasures::SKIPPED_TEMP_PRESS,
+            322858,
+            23549,
+        );
+        assert_eq!(None, measurement.temperature);
+        assert_eq!(None, measurement.pressure);
+        assert_eq!(None, measurement.humidity);
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let config = Bme280Config {
+            temp_oversampling: Oversampling::ModuleDisabled,
+            ..Default::default()
+        };
+        assert_eq!(Err(ConfigError::TemperatureDisabled), config.validate());
+        assert!(matches!(
+            Bme280::init(DummyInterface {}, 0x76, DummyDelay {}, config),
+            Err(Bme280Error::InvalidConfig(ConfigError::TemperatureDisabled))
+        ));
+
+        let config = Bme280Config {
+            temp_oversampling: Oversampling::X1,
+            press_oversampling: Oversampling::X1,
+            ..Default::default()
+        };
+        assert_eq!(Ok(()), config.validate());
+        assert_eq!(Ok(()), Bme280Config::default().validate());
+
+        let config = Bme280Config {
+            hum_oversampling: Oversampling::ModuleDisabled,
+            temp_oversampling: Oversampling::ModuleDisabled,
+            press_oversampling: Oversampling::ModuleDisabled,
+            ..Default::default()
+        };
+        assert_eq!(Err(ConfigError::NothingMeasured), config.validate());
+    }
+
     #[test]
     fn test_compensate_t() {
         // Magic numbers obtained by dumping values from proofed to work bme280 lib
@@ -401,8 +1714,7 @@ mod tests {
         let expected_t_fine = 110074;
         let expected_t = 2150;
 
-        let (t_fine, t) =
-            Bme280::<DummyInterface, DummyDelay>::compensate_t(MOCK_CALIB_DATA, mock_adc_t);
+        let (t_fine, t) = Compensator::new(*MOCK_CALIB_DATA).compensate_t(mock_adc_t);
         assert_eq!(expected_t_fine, t_fine);
         assert_eq!(expected_t, t);
     }
@@ -414,14 +1726,52 @@ mod tests {
         let mock_t_fine = 120188;
         let expected_p = I24F8(26110518);
 
-        let p = Bme280::<DummyInterface, DummyDelay>::compensate_p(
-            MOCK_CALIB_DATA,
-            mock_t_fine,
-            mock_adc_p,
-        );
+        let p = Compensator::new(*MOCK_CALIB_DATA).compensate_p(mock_t_fine, mock_adc_p);
         assert_eq!(expected_p, p);
     }
 
+    #[test]
+    fn test_compensate_p_32() {
+        // Example from BMP 280 datasheet chapter 3.12
+        let calib_data = &CalibData {
+            dig_t1: 27504,
+            dig_t2: 26435,
+            dig_t3: -1000,
+            dig_p1: 36477,
+            dig_p2: -10685,
+            dig_p3: 3024,
+            dig_p4: 2855,
+            dig_p5: 140,
+            dig_p6: -7,
+            dig_p7: 15500,
+            dig_p8: -14600,
+            dig_p9: 6000,
+            ..*MOCK_CALIB_DATA
+        };
+        let (t_fine, _) = Compensator::new(*calib_data).compensate_t(519888);
+        assert_eq!(128422, t_fine);
+        let p = Compensator::new(*calib_data).compensate_p_32(t_fine, 415148);
+        assert_eq!(100653, p);
+
+        // 32-bit formula loses few Pa of accuracy, most of all at low temperature. Results are
+        // compared over whole ADC range wherever 64-bit one is within sensor operating range of
+        // 300..1100 hPa, at -40, 25 and 85 °C
+        for (t_fine, tolerance) in [(-204800, 6), (128000, 2), (435200, 2)] {
+            for adc_p in 0..=0xFFFFF {
+                let p64 = Compensator::new(*MOCK_CALIB_DATA).compensate_p(t_fine, adc_p);
+                let p32 = Compensator::new(*MOCK_CALIB_DATA).compensate_p_32(t_fine, adc_p);
+
+                let p64 = p64.get_int() as i64;
+                if (30000..=110000).contains(&p64) {
+                    assert!(
+                        (p64 - p32 as i64).abs() <= tolerance,
+                        "t_fine {t_fine}, adc_p {adc_p}: {p64} vs {p32}"
+                    );
+                }
+            }
+        }
+    }
+
     #[test]
     fn test_compensate_h() {
         // Magic numbers obtained by dumping values from proofed to work bme280 lib
@@ -429,11 +1779,587 @@ mod tests {
         let mock_t_fine = 99523;
         let expected_h = I22F10(27726);
 
-        let p = Bme280::<DummyInterface, DummyDelay>::compensate_h(
-            MOCK_CALIB_DATA,
-            mock_t_fine,
-            mock_adc_h,
-        );
+        let p = Compensator::new(*MOCK_CALIB_DATA).compensate_h(mock_t_fine, mock_adc_h);
         assert_eq!(expected_h, p);
     }
+
+    #[test]
+    fn test_compensate_sub_zero() {
+        let mut regs = mock_regs();
+        // adc_t 400000
+        regs[0xFA..=0xFC].copy_from_slice(&[0x61, 0xA8, 0x00]);
+        let raw_measures = RawMeasures(regs[0xF7..=0xFE].try_into().unwrap());
+        let measurement = Compensator::new(*MOCK_CALIB_DATA).compensate_raw(&raw_measures);
+
+        let (_, expected_t) = Compensator::new(*MOCK_CALIB_DATA).compensate_t(400000);
+        assert!(expected_t < 0);
+        assert!((measurement.temperature.unwrap().to_milli() - expected_t as i64 * 10).abs() <= 1);
+        assert!(measurement.temperature.unwrap() < I22F10::new(0, 0));
+    }
+
+    #[test]
+    fn test_fixed_point_arithmetic() {
+        let t = I22F10::new(-1025, 0) / I22F10::new(100, 0);
+        assert_eq!(-11, t.get_int());
+        assert_eq!(-10250, t.to_milli());
+        assert!((t.to_f32() + 10.25).abs() < 0.001);
+
+        assert_eq!(I22F10::new(-3, 0), I22F10::new(-1, 0) - I22F10::new(2, 0));
+        assert_eq!(I22F10::new(1, 0), I22F10::new(-1, 0) + I22F10::new(2, 0));
+        assert_eq!(I22F10::new(-6, 0), I22F10::new(-2, 0) * I22F10::new(3, 0));
+        assert_eq!(I22F10::new(2, 0), -I22F10::new(-2, 0));
+        assert_eq!(I24F8::new(-3, 128), I24F8::new(-5, 0) / I24F8::new(2, 0));
+        assert_eq!(-2500, I24F8::new(-3, 128).to_milli());
+    }
+
+    #[test]
+    fn test_fixed_point_ordering() {
+        assert!(I22F10::new(-1, 0) < I22F10::new(0, 0));
+        assert!(I22F10::new(-2, 0) < I22F10::new(-1, 0));
+        assert!(I24F8::new(-1, 0) < I24F8::new(1, 0));
+        assert_eq!(
+            Some(I22F10::new(-2, 0)),
+            [I22F10::new(3, 0), I22F10::new(-2, 0), I22F10::new(0, 0)]
+                .into_iter()
+                .min()
+        );
+    }
+
+    #[test]
+    fn test_fixed_point_overflow() {
+        let max = I24F8::from_bits(i32::MAX);
+        let min = I24F8::from_bits(i32::MIN);
+        let one = I24F8::new(1, 0);
+        let two = I24F8::new(2, 0);
+
+        assert_eq!(None, max.checked_add(one));
+        assert_eq!(None, min.checked_sub(one));
+        assert_eq!(None, max.checked_mul(two));
+        assert_eq!(None, one.checked_div(I24F8::new(0, 0)));
+        assert_eq!(None, max.checked_div(I24F8::new(0, 128)));
+        assert_eq!(None, min.checked_neg());
+        assert_eq!(Some(I24F8::new(3, 0)), one.checked_add(two));
+
+        assert_eq!(max, max.saturating_add(one));
+        assert_eq!(min, min.saturating_sub(one));
+        assert_eq!(min, max.saturating_mul(-two));
+        assert_eq!(max, max.saturating_div(I24F8::new(0, 128)));
+        assert_eq!(min, max + I24F8::from_bits(1));
+    }
+
+    #[test]
+    fn test_measurement_time() {
+        let mut config = Bme280Config {
+            hum_oversampling: Oversampling::X1,
+            temp_oversampling: Oversampling::X1,
+            press_oversampling: Oversampling::X1,
+            ..Default::default()
+        };
+        assert_eq!(
+            MeasurementTime {
+                typical_us: 8000,
+                max_us: 9300
+            },
+            config.measurement_time()
+        );
+
+        config.hum_oversampling = Oversampling::X16;
+        config.temp_oversampling = Oversampling::X16;
+        config.press_oversampling = Oversampling::X16;
+        assert_eq!(
+            MeasurementTime {
+                typical_us: 98000,
+                max_us: 112800
+            },
+            config.measurement_time()
+        );
+
+        // Skipped pressure and humidity
+        config.hum_oversampling = Oversampling::ModuleDisabled;
+        config.temp_oversampling = Oversampling::X2;
+        config.press_oversampling = Oversampling::ModuleDisabled;
+        assert_eq!(
+            MeasurementTime {
+                typical_us: 5000,
+                max_us: 5850
+            },
+            config.measurement_time()
+        );
+    }
+
+    #[test]
+    fn test_measurement_timeout() {
+        let config = Bme280Config {
+            temp_oversampling: Oversampling::X1,
+            ..Default::default()
+        };
+        let mut bme280 = Bme280::init(MockInterface::new(), 0x76, NoopDelay {}, config).unwrap();
+
+        // Floating bus
+        bme280.interface.regs[Status::START_ADDR as usize] = 0xFF;
+        assert!(matches!(bme280.do_measurement(), Err(Bme280Error::Timeout)));
+    }
+
+    #[test]
+    fn test_nvm_copy_timeout() {
+        let mut interface = MockInterface::new();
+        interface.regs[Status::START_ADDR as usize] = 0b00000001;
+        assert!(matches!(
+            Bme280::init(interface, 0x76, NoopDelay {}, Bme280Config::default()),
+            Err(Bme280Error::NvmCopyTimeout)
+        ));
+    }
+
+    #[test]
+    fn test_nvm_copy_wait() {
+        let mut interface = MockInterface::new();
+        interface.nvm_copy_reads = 3;
+        let bme280 = Bme280::init(interface, 0x76, NoopDelay {}, Bme280Config::default()).unwrap();
+        assert_eq!(MOCK_CALIB_DATA, bme280.get_calib());
+    }
+
+    #[test]
+    fn test_soft_reset() {
+        let mut calib_data = *MOCK_CALIB_DATA;
+        calib_data.dig_t1 += 1;
+        let mut bme280 = Bme280::init_with_calib(
+            MockInterface::new(),
+            0x76,
+            NoopDelay {},
+            Bme280Config::indoor_navigation(),
+            calib_data,
+        )
+        .unwrap();
+
+        // Reset clears control registers
+        bme280.interface.regs[CtrlMeas::START_ADDR as usize] = 0;
+        bme280.interface.regs[Config::START_ADDR as usize] = 0;
+        bme280.interface.nvm_copy_reads = 2;
+        bme280.soft_reset().unwrap();
+        assert_eq!(MOCK_CALIB_DATA, bme280.get_calib());
+        assert_eq!(
+            0b00010000,
+            bme280.interface.regs[Config::START_ADDR as usize]
+        );
+        assert_eq!(
+            0b01010111,
+            bme280.interface.regs[CtrlMeas::START_ADDR as usize]
+        );
+
+        bme280.interface.nvm_copy_reads = usize::MAX;
+        assert!(matches!(
+            bme280.soft_reset(),
+            Err(Bme280Error::NvmCopyTimeout)
+        ));
+    }
+
+    #[test]
+    fn test_bmp280() {
+        let mut interface = MockInterface::new();
+        interface.regs[Id::START_ADDR as usize] = Id::BMP280_MASS_PRODUCTION_ID;
+        let config = Bme280Config {
+            hum_oversampling: Oversampling::X1,
+            temp_oversampling: Oversampling::X1,
+            press_oversampling: Oversampling::X1,
+            ..Default::default()
+        };
+
+        let mut bmp280 = Bme280::init(interface, 0x76, NoopDelay {}, config).unwrap();
+        assert_eq!(ChipVariant::Bmp280, bmp280.chip());
+        // Humidity calibration and control are left untouched
+        assert_eq!(0, bmp280.get_calib().dig_h2);
+        assert_eq!(0, bmp280.interface.regs[CtrlHum::START_ADDR as usize]);
+        assert_eq!(5500, bmp280.config.measurement_time().typical_us);
+
+        let measurement = bmp280.do_measurement().unwrap();
+        assert_eq!(21, measurement.temperature.unwrap().get_int());
+        assert_eq!(None, measurement.humidity);
+    }
+
+    #[test]
+    fn test_bmp280_standby() {
+        let config = Bme280Config {
+            mode: MeasurementMode::Normal,
+            standby_period: StandbyPeriod::Ms10,
+            ..Default::default()
+        };
+        let bme280 = Bme280::init(MockInterface::new(), 0x76, NoopDelay {}, config).unwrap();
+        assert_eq!(0b110, Config(bme280.interface.regs[0xF5]).get_t_sb());
+
+        // Same code is 2000 ms standby on BMP280
+        let mut interface = MockInterface::new();
+        interface.regs[Id::START_ADDR as usize] = Id::BMP280_MASS_PRODUCTION_ID;
+        assert!(matches!(
+            Bme280::init(interface, 0x76, NoopDelay {}, config),
+            Err(Bme280Error::InvalidConfig(ConfigError::StandbyNotSupported))
+        ));
+
+        let mut interface = MockInterface::new();
+        interface.regs[Id::START_ADDR as usize] = Id::BMP280_MASS_PRODUCTION_ID;
+        let mut bmp280 = Bme280::init(interface, 0x76, NoopDelay {}, Default::default()).unwrap();
+        let config = Bme280Config {
+            standby_period: StandbyPeriod::Ms20,
+            ..Default::default()
+        };
+        assert!(matches!(
+            bmp280.reconfigure(config),
+            Err(Bme280Error::InvalidConfig(ConfigError::StandbyNotSupported))
+        ));
+        assert_eq!(StandbyPeriod::Us500, bmp280.config.standby_period);
+        assert_eq!(0, Config(bmp280.interface.regs[0xF5]).get_t_sb());
+    }
+
+    #[cfg(feature = "float")]
+    #[test]
+    fn test_do_measurement_f32() {
+        let config = Bme280Config {
+            hum_oversampling: Oversampling::X1,
+            temp_oversampling: Oversampling::X1,
+            press_oversampling: Oversampling::X1,
+            ..Default::default()
+        };
+        let mut bme280 = Bme280::init(MockInterface::new(), 0x76, NoopDelay {}, config).unwrap();
+
+        let fixed = bme280.do_measurement().unwrap();
+        let float = bme280.do_measurement_f32().unwrap();
+        let temperature = fixed.temperature.unwrap().to_f32();
+        assert!((float.temperature.unwrap() - temperature).abs() < 0.01);
+        // 32-bit pressure formula is only accurate to a couple of Pa
+        let tolerance = if cfg!(feature = "pressure-i32") {
+            2.0
+        } else {
+            1.0
+        };
+        let pressure = fixed.pressure.unwrap().to_f32();
+        assert!((float.pressure.unwrap() - pressure).abs() < tolerance);
+        let humidity = fixed.humidity.unwrap().to_f32();
+        assert!((float.humidity.unwrap() - humidity).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_unknown_chip() {
+        let mut interface = MockInterface::new();
+        interface.regs[Id::START_ADDR as usize] = 0xFF;
+        assert!(matches!(
+            Bme280::init(interface, 0x76, NoopDelay {}, Bme280Config::default()),
+            Err(Bme280Error::IdDoesNotMatch)
+        ));
+    }
+
+    #[test]
+    fn test_normal_mode() {
+        let config = Bme280Config {
+            hum_oversampling: Oversampling::X1,
+            temp_oversampling: Oversampling::X2,
+            press_oversampling: Oversampling::X16,
+            standby_period: StandbyPeriod::Ms125,
+            filter: Filter::C4,
+            ..Default::default()
+        };
+        let mut bme280 = Bme280::init(MockInterface::new(), 0x76, NoopDelay {}, config).unwrap();
+        assert_eq!(
+            0b01001000,
+            bme280.interface.regs[Config::START_ADDR as usize]
+        );
+        assert_eq!(
+            0b01010100,
+            bme280.interface.regs[CtrlMeas::START_ADDR as usize]
+        );
+
+        bme280.start_normal().unwrap();
+        assert_eq!(
+            0b01010111,
+            bme280.interface.regs[CtrlMeas::START_ADDR as usize]
+        );
+
+        let measurement = bme280.read_latest().unwrap();
+        assert_eq!(21, measurement.temperature.unwrap().get_int());
+    }
+
+    #[test]
+    fn test_reconfigure() {
+        let mut bme280 =
+            Bme280::init(MockInterface::new(), 0x76, NoopDelay {}, Default::default()).unwrap();
+        bme280.interface.writes_len = 0;
+
+        bme280
+            .reconfigure(Bme280Config::indoor_navigation())
+            .unwrap();
+        assert_eq!(
+            0b00000001,
+            bme280.interface.regs[CtrlHum::START_ADDR as usize]
+        );
+        assert_eq!(
+            0b00010000,
+            bme280.interface.regs[Config::START_ADDR as usize]
+        );
+        assert_eq!(
+            0b01010111,
+            bme280.interface.regs[CtrlMeas::START_ADDR as usize]
+        );
+        // ctrl_hum is only latched by following ctrl_meas write, and config is written
+        // after sensor is put to sleep
+        assert_eq!(
+            [
+                CtrlMeas::START_ADDR,
+                Config::START_ADDR,
+                CtrlHum::START_ADDR,
+                CtrlMeas::START_ADDR,
+                CtrlMeas::START_ADDR,
+            ],
+            bme280.interface.writes()
+        );
+
+        // Back to sleep in forced mode
+        bme280
+            .reconfigure(Bme280Config::humidity_sensing())
+            .unwrap();
+        assert_eq!(
+            0b00100000,
+            bme280.interface.regs[CtrlMeas::START_ADDR as usize]
+        );
+
+        assert!(matches!(
+            bme280.reconfigure(Bme280Config {
+                temp_oversampling: Oversampling::ModuleDisabled,
+                ..Default::default()
+            }),
+            Err(Bme280Error::InvalidConfig(ConfigError::TemperatureDisabled))
+        ));
+    }
+
+    #[test]
+    fn test_reconfigure_read_back() {
+        let mut bme280 =
+            Bme280::init(MockInterface::new(), 0x76, NoopDelay {}, Default::default()).unwrap();
+        bme280.interface.read_only = Some(Config::START_ADDR);
+
+        assert!(matches!(
+            bme280.reconfigure(Bme280Config::gaming()),
+            Err(Bme280Error::ConfigNotApplied)
+        ));
+        // Previous config is kept, so measurements wait for the right duration
+        assert_eq!(Bme280Config::default(), bme280.config);
+        assert_eq!(
+            Bme280Config::default().measurement_time(),
+            bme280.config.measurement_time()
+        );
+        // Unchanged config is still applied
+        bme280.reconfigure(Default::default()).unwrap();
+    }
+
+    #[test]
+    fn test_free() {
+        let bme280 =
+            Bme280::init(MockInterface::new(), 0x76, NoopDelay {}, Default::default()).unwrap();
+        let (interface, _delay) = bme280.free();
+        assert_eq!(0b00100100, interface.regs[CtrlMeas::START_ADDR as usize]);
+    }
+
+    #[test]
+    fn test_detached() {
+        let mut interface = MockInterface::new();
+        let mut delay = NoopDelay {};
+        let mut bme280 =
+            DetachedBme280::init(&mut interface, 0x76, &mut delay, Default::default()).unwrap();
+
+        // Bus is free between calls
+        interface.writes_len = 0;
+        let measurement = bme280
+            .with(&mut interface, &mut delay, |bme280| bme280.do_measurement())
+            .unwrap();
+        assert_eq!(21, measurement.temperature.unwrap().get_int());
+        assert_eq!([CtrlMeas::START_ADDR], interface.writes());
+
+        bme280
+            .with(&mut interface, &mut delay, |bme280| {
+                bme280.reconfigure(Bme280Config::gaming())
+            })
+            .unwrap();
+        assert_eq!(Bme280Config::gaming(), bme280.config);
+
+        let mut bme280 = bme280.attach(interface, delay);
+        assert_eq!(MeasurementMode::Normal, bme280.config.mode);
+        let measurement = bme280.read_latest().unwrap();
+        assert_eq!(21, measurement.temperature.unwrap().get_int());
+    }
+
+    // Sensor answering only at given I2C address
+    struct AddressedInterface {
+        address: u8,
+        interface: MockInterface,
+    }
+
+    impl embedded_hal::blocking::i2c::WriteRead for AddressedInterface {
+        type Error = ();
+
+        fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), ()> {
+            if address != self.address {
+                return Err(());
+            }
+            self.interface.reg_read(address, bytes[0], buffer)
+        }
+    }
+
+    #[test]
+    fn test_probe() {
+        use crate::submodules::i2c::probe;
+
+        let mut interface = AddressedInterface {
+            address: 0x77,
+            interface: MockInterface::new(),
+        };
+        assert_eq!(
+            [
+                (Address::Primary, None),
+                (Address::Secondary, Some(ChipVariant::Bme280))
+            ],
+            probe(&mut interface)
+        );
+        assert_eq!(0, interface.interface.writes_len);
+
+        interface.address = 0x76;
+        interface.interface.regs[Id::START_ADDR as usize] = Id::BMP280_MASS_PRODUCTION_ID;
+        assert_eq!(
+            [
+                (Address::Primary, Some(ChipVariant::Bmp280)),
+                (Address::Secondary, None)
+            ],
+            probe(&mut interface)
+        );
+
+        // Other device answering at sensor address
+        interface.interface.regs[Id::START_ADDR as usize] = 0x42;
+        assert_eq!(
+            [(Address::Primary, None), (Address::Secondary, None)],
+            probe(&mut interface)
+        );
+
+        assert_eq!(0x40, u8::from(Address::Custom(0x40)));
+        // Driver accepts both raw and named addresses
+        let bme280 = Bme280::init(
+            MockInterface::new(),
+            Address::Primary,
+            NoopDelay {},
+            Default::default(),
+        )
+        .unwrap();
+        assert_eq!(0x76, bme280.dev_addr);
+    }
+
+    #[test]
+    fn test_compensate_adc_t_range() {
+        // Out of range readings, e.g. 0xFFFFF from floating bus, give garbage but don't panic
+        let compensator = Compensator::new(*MOCK_CALIB_DATA);
+        for adc_t in 0..=0xFFFFF {
+            let measurement = compensator.compensate(adc_t, 322858, 23549);
+            // Both pressure formulas, whichever one is enabled
+            compensator.compensate_p_32(measurement.t_fine, 322858);
+            compensator.compensate_p(measurement.t_fine, 322858);
+        }
+    }
+
+    #[test]
+    fn test_presets() {
+        for config in [
+            Bme280Config::weather_monitoring(),
+            Bme280Config::humidity_sensing(),
+            Bme280Config::indoor_navigation(),
+            Bme280Config::gaming(),
+        ] {
+            assert_eq!(Ok(()), config.validate());
+        }
+        assert_eq!(Bme280Config::weather_monitoring(), Bme280Config::default());
+
+        let bme280 = Bme280::init(
+            MockInterface::new(),
+            0x76,
+            NoopDelay {},
+            Bme280Config::indoor_navigation(),
+        )
+        .unwrap();
+        let regs = &bme280.interface.regs;
+        assert_eq!(0b001, regs[CtrlHum::START_ADDR as usize]);
+        assert_eq!(0b01010111, regs[CtrlMeas::START_ADDR as usize]);
+        assert_eq!(0b00010000, regs[Config::START_ADDR as usize]);
+
+        let bme280 = Bme280::init(
+            MockInterface::new(),
+            0x76,
+            NoopDelay {},
+            Bme280Config::humidity_sensing(),
+        )
+        .unwrap();
+        // Forced mode sensor sleeps until conversion is triggered
+        assert_eq!(
+            0b00100000,
+            bme280.interface.regs[CtrlMeas::START_ADDR as usize]
+        );
+    }
+
+    #[test]
+    fn test_config_builder() {
+        let config = Bme280Config::builder()
+            .press_oversampling(Oversampling::X8)
+            .filter(Filter::C4)
+            .mode(MeasurementMode::Normal)
+            .standby_period(StandbyPeriod::Ms1000)
+            .build()
+            .unwrap();
+        assert_eq!(Oversampling::X1, config.temp_oversampling);
+        assert_eq!(Oversampling::X8, config.press_oversampling);
+        assert_eq!(MeasurementMode::Normal, config.mode);
+
+        let config = Bme280Config::gaming()
+            .into_builder()
+            .filter(Filter::Off)
+            .build()
+            .unwrap();
+        assert_eq!(
+            Bme280Config {
+                filter: Filter::Off,
+                ..Bme280Config::gaming()
+            },
+            config
+        );
+
+        assert_eq!(
+            Err(ConfigError::TemperatureDisabled),
+            Bme280Config::builder()
+                .temp_oversampling(Oversampling::ModuleDisabled)
+                .build()
+        );
+    }
+
+    #[test]
+    fn test_poll_measurement() {
+        let config = Bme280Config {
+            temp_oversampling: Oversampling::X1,
+            ..Default::default()
+        };
+        let mut bme280 = Bme280::init(MockInterface::new(), 0x76, NoopDelay {}, config).unwrap();
+
+        bme280.start_measurement().unwrap();
+        assert_eq!(
+            0b00100101,
+            bme280.interface.regs[CtrlMeas::START_ADDR as usize]
+        );
+
+        bme280.interface.regs[Status::START_ADDR as usize] = 0b00001000;
+        assert!(matches!(
+            bme280.poll_measurement(),
+            Err(nb::Error::WouldBlock)
+        ));
+
+        bme280.interface.regs[Status::START_ADDR as usize] = 0;
+        let measurement = bme280.poll_measurement().unwrap();
+        assert_eq!(21, measurement.temperature.unwrap().get_int());
+
+        // Logged raw readings give same result on host
+        let compensator = bme280.compensator();
+        assert_eq!(
+            measurement,
+            compensator.compensate(measurement.adc_t, measurement.adc_p, measurement.adc_h)
+        );
+    }
 }
@@ -0,0 +1,407 @@
+use crate::{Measurement, I22F10, I24F8};
+
+/// Standard atmosphere pressure at sea level, in Pa
+pub const STANDARD_SEA_LEVEL_PRESSURE: I24F8 = I24F8::from_bits(101325 << 8);
+
+// Standard atmosphere pressure in Pa as `I24F8` bits, from -1000 m to 20000 m in 100 m steps,
+// covering troposphere and isothermal stratosphere layer above 11000 m. Linear interpolation
+// between points stays within 0.5 m of the formulas
+const ISA_PRESSURE: [i64; 211] = [
+    29165848, 28829268, 28495840, 28165541, 27838350, 27514243, 27193198, 26875194, 26560207,
+    26248216, 25939200, 25633136, 25330003, 25029779, 24732443, 24437974, 24146350, 23857550,
+    23571554, 23288340, 23007888, 22730177, 22455186, 22182896, 21913285, 21646334, 21382023,
+    21120330, 20861238, 20604725, 20350772, 20099359, 19850467, 19604077, 19360169, 19118724,
+    18879724, 18643148, 18408978, 18177196, 17947783, 17720719, 17495988, 17273570, 17053446,
+    16835600, 16620013, 16406666, 16195543, 15986625, 15779894, 15575334, 15372926, 15172653,
+    14974499, 14778445, 14584474, 14392570, 14202717, 14014896, 13829091, 13645286, 13463464,
+    13283610, 13105705, 12929735, 12755683, 12583533, 12413269, 12244875, 12078336, 11913636,
+    11750759, 11589690, 11430413, 11272914, 11117176, 10963185, 10810926, 10660384, 10511543,
+    10364390, 10218909, 10075087, 9932908, 9792358, 9653422, 9516087, 9380339, 9246163, 9113545,
+    8982471, 8852929, 8724903, 8598380, 8473347, 8349791, 8227698, 8107054, 7987846, 7870062,
+    7753689, 7638712, 7525120, 7412900, 7302039, 7192524, 7084343, 6977483, 6871932, 6767678,
+    6664708, 6563010, 6462573, 6363384, 6265431, 6168703, 6073188, 5978874, 5885749, 5793802,
+    5703157, 5613930, 5526099, 5439642, 5354538, 5270765, 5188303, 5107131, 5027230, 4948578,
+    4871156, 4794946, 4719928, 4646084, 4573395, 4501844, 4431412, 4362082, 4293836, 4226658,
+    4160531, 4095439, 4031365, 3968294, 3906209, 3845096, 3784939, 3725723, 3667433, 3610055,
+    3553575, 3497979, 3443253, 3389382, 3336355, 3284157, 3232776, 3182198, 3132412, 3083405,
+    3035165, 2987679, 2940936, 2894925, 2849633, 2805050, 2761165, 2717966, 2675443, 2633585,
+    2592382, 2551824, 2511900, 2472601, 2433917, 2395838, 2358354, 2321458, 2285138, 2249387,
+    2214195, 2179553, 2145454, 2111888, 2078847, 2046323, 2014308, 1982794, 1951772, 1921237,
+    1891178, 1861591, 1832466, 1803796, 1775576, 1747797, 1720452, 1693535, 1667040, 1640958,
+    1615285, 1590014, 1565138, 1540651, 1516547, 1492821, 1469465, 1446475, 1423845, 1401568,
+];
+const ISA_START_M: i64 = -1000;
+const ISA_STEP_M: i64 = 100;
+
+// 273.15, 288.15 (standard sea level temperature) and 216.65 (stratosphere temperature) as
+// `I22F10` bits
+const KELVIN_OFFSET: i64 = 279706;
+const ISA_T0: i64 = 295066;
+const ISA_T_STRATOSPHERE: i64 = 221850;
+
+// `I22F10` bits of altitude with given standard atmosphere pressure, clamped to the table
+fn isa_altitude(pressure: i64) -> i64 {
+    let pressure = pressure.clamp(ISA_PRESSURE[ISA_PRESSURE.len() - 1], ISA_PRESSURE[0]);
+    let i = ISA_PRESSURE[1..ISA_PRESSURE.len() - 1]
+        .iter()
+        .take_while(|&&p| p > pressure)
+        .count();
+    let (p0, p1) = (ISA_PRESSURE[i], ISA_PRESSURE[i + 1]);
+    let h0 = (ISA_START_M + i as i64 * ISA_STEP_M) << 10;
+
+    h0 + (p0 - pressure) * (ISA_STEP_M << 10) / (p0 - p1)
+}
+
+// `I24F8` bits of standard atmosphere pressure at given altitude, clamped to the table
+fn isa_pressure(altitude: i64) -> i64 {
+    let top = ISA_START_M + (ISA_PRESSURE.len() as i64 - 1) * ISA_STEP_M;
+    let altitude = altitude.clamp(ISA_START_M << 10, top << 10);
+    let i = ((altitude - (ISA_START_M << 10)) / (ISA_STEP_M << 10))
+        .clamp(0, ISA_PRESSURE.len() as i64 - 2);
+    let (p0, p1) = (ISA_PRESSURE[i as usize], ISA_PRESSURE[i as usize + 1]);
+    let h0 = (ISA_START_M + i * ISA_STEP_M) << 10;
+
+    p0 - (p0 - p1) * (altitude - h0) / (ISA_STEP_M << 10)
+}
+
+/// Barometric altitude in m, relative to level where pressure is `sea_level` (QNH for
+/// altitude above sea level, QFE for height above airfield). Valid from -1000 m to 20000 m
+/// of standard atmosphere, result is clamped to this range. `None` if `sea_level` is not
+/// positive
+pub fn altitude(pressure: I24F8, sea_level: I24F8) -> Option<I22F10> {
+    if sea_level.to_bits() <= 0 {
+        return None;
+    }
+    // Altitude depends only on pressure ratio, so pressure is rescaled to standard sea level
+    let pressure = pressure.to_bits() as i64 * STANDARD_SEA_LEVEL_PRESSURE.to_bits() as i64
+        / sea_level.to_bits() as i64;
+    Some(I22F10::from_bits(isa_altitude(pressure) as i32))
+}
+
+/// Same as [`altitude`], but with air column at measured `temperature` (°C) instead of
+/// standard atmosphere one. Both are equal when temperature matches standard atmosphere
+pub fn altitude_temp_corrected(
+    pressure: I24F8,
+    sea_level: I24F8,
+    temperature: I22F10,
+) -> Option<I22F10> {
+    let altitude = altitude(pressure, sea_level)?.to_bits() as i64;
+    let temperature = temperature.to_bits() as i64 + KELVIN_OFFSET;
+    // Standard atmosphere temperature at altitude, with 0.0065 K/m lapse rate up to
+    // stratosphere
+    let isa_temperature = (ISA_T0 - altitude * 65 / 10000).max(ISA_T_STRATOSPHERE);
+    Some(I22F10::from_bits(
+        (altitude * temperature / isa_temperature) as i32,
+    ))
+}
+
+/// Pressure reduced from `elevation` (m) to reference level with standard atmosphere.
+/// Gives QNH for elevation above sea level, or QFE for sensor height above airfield.
+/// Elevation is clamped to the range of [`altitude`]
+pub fn sea_level_pressure(pressure: I24F8, elevation: I22F10) -> I24F8 {
+    let isa_pressure = isa_pressure(elevation.to_bits() as i64);
+    let pressure =
+        pressure.to_bits() as i64 * STANDARD_SEA_LEVEL_PRESSURE.to_bits() as i64 / isa_pressure;
+    I24F8::from_bits(pressure.clamp(i32::MIN as i64, i32::MAX as i64) as i32)
+}
+
+/// Altitude in m relative to standard pressure (1013.25 hPa), clamped to the range of
+/// [`altitude`]
+pub fn pressure_altitude(pressure: I24F8) -> I22F10 {
+    I22F10::from_bits(isa_altitude(pressure.to_bits() as i64) as i32)
+}
+
+/// Pressure altitude in hundreds of feet, rounded to nearest
+pub fn flight_level(pressure: I24F8) -> i32 {
+    // 100 ft is 30.48 m
+    let divisor = 3048 << 10;
+    ((pressure_altitude(pressure).to_bits() as i64 * 100 + divisor / 2).div_euclid(divisor)) as i32
+}
+
+impl Measurement {
+    /// See [`altitude`]
+    pub fn altitude(&self, sea_level: I24F8) -> Option<I22F10> {
+        altitude(self.pressure?, sea_level)
+    }
+
+    /// See [`altitude_temp_corrected`]
+    pub fn altitude_temp_corrected(&self, sea_level: I24F8) -> Option<I22F10> {
+        altitude_temp_corrected(self.pressure?, sea_level, self.temperature?)
+    }
+
+    /// See [`sea_level_pressure`]
+    pub fn sea_level_pressure(&self, elevation: I22F10) -> Option<I24F8> {
+        Some(sea_level_pressure(self.pressure?, elevation))
+    }
+
+    /// See [`pressure_altitude`]
+    pub fn pressure_altitude(&self) -> Option<I22F10> {
+        Some(pressure_altitude(self.pressure?))
+    }
+
+    /// See [`flight_level`]
+    pub fn flight_level(&self) -> Option<i32> {
+        Some(flight_level(self.pressure?))
+    }
+}
+
+#[cfg(feature = "float")]
+mod float {
+    use crate::MeasurementF32;
+    use libm::{expf, logf, powf};
+
+    /// Standard atmosphere pressure at sea level, in Pa
+    pub const STANDARD_SEA_LEVEL_PRESSURE_F32: f32 = 101325.0;
+
+    // Standard atmosphere sea level temperature over lapse rate (288.15 K / 0.0065 K/m), and
+    // R * L / (g * M)
+    const ISA_HEIGHT: f32 = 44330.77;
+    const BARO_EXPONENT: f32 = 0.190263;
+    // Stratosphere base altitude and pressure, and its scale height R * T / (g * M) at
+    // 216.65 K
+    const TROPOPAUSE: f32 = 11000.0;
+    const TROPOPAUSE_PRESSURE: f32 = 22632.04;
+    const STRATOSPHERE_SCALE_HEIGHT: f32 = 6341.62;
+    // Same range as integer functions
+    const ISA_MIN_ALTITUDE: f32 = -1000.0;
+    const ISA_MAX_ALTITUDE: f32 = 20000.0;
+
+    fn isa_altitude_f32(pressure: f32) -> f32 {
+        let altitude = if pressure >= TROPOPAUSE_PRESSURE {
+            ISA_HEIGHT * (1.0 - powf(pressure / STANDARD_SEA_LEVEL_PRESSURE_F32, BARO_EXPONENT))
+        } else {
+            TROPOPAUSE + STRATOSPHERE_SCALE_HEIGHT * logf(TROPOPAUSE_PRESSURE / pressure)
+        };
+        altitude.clamp(ISA_MIN_ALTITUDE, ISA_MAX_ALTITUDE)
+    }
+
+    fn isa_pressure_f32(altitude: f32) -> f32 {
+        let altitude = altitude.clamp(ISA_MIN_ALTITUDE, ISA_MAX_ALTITUDE);
+        if altitude <= TROPOPAUSE {
+            STANDARD_SEA_LEVEL_PRESSURE_F32 * powf(1.0 - altitude / ISA_HEIGHT, 1.0 / BARO_EXPONENT)
+        } else {
+            TROPOPAUSE_PRESSURE * expf((TROPOPAUSE - altitude) / STRATOSPHERE_SCALE_HEIGHT)
+        }
+    }
+
+    /// Floating point version of [`super::altitude`], NaN if `sea_level` is not positive
+    pub fn altitude_f32(pressure: f32, sea_level: f32) -> f32 {
+        if sea_level <= 0.0 {
+            return f32::NAN;
+        }
+        isa_altitude_f32(pressure * STANDARD_SEA_LEVEL_PRESSURE_F32 / sea_level)
+    }
+
+    /// Floating point version of [`super::altitude_temp_corrected`], temperature in °C
+    pub fn altitude_temp_corrected_f32(pressure: f32, sea_level: f32, temperature: f32) -> f32 {
+        let altitude = altitude_f32(pressure, sea_level);
+        let isa_temperature = (288.15 - altitude * 0.0065).max(216.65);
+        altitude * (temperature + 273.15) / isa_temperature
+    }
+
+    /// Floating point version of [`super::sea_level_pressure`]
+    pub fn sea_level_pressure_f32(pressure: f32, elevation: f32) -> f32 {
+        pressure * STANDARD_SEA_LEVEL_PRESSURE_F32 / isa_pressure_f32(elevation)
+    }
+
+    /// Floating point version of [`super::pressure_altitude`]
+    pub fn pressure_altitude_f32(pressure: f32) -> f32 {
+        isa_altitude_f32(pressure)
+    }
+
+    /// Pressure altitude in hundreds of feet, not rounded
+    pub fn flight_level_f32(pressure: f32) -> f32 {
+        pressure_altitude_f32(pressure) / 30.48
+    }
+
+    impl MeasurementF32 {
+        /// See [`altitude_f32`]
+        pub fn altitude(&self, sea_level: f32) -> Option<f32> {
+            Some(altitude_f32(self.pressure?, sea_level))
+        }
+
+        /// See [`altitude_temp_corrected_f32`]
+        pub fn altitude_temp_corrected(&self, sea_level: f32) -> Option<f32> {
+            Some(altitude_temp_corrected_f32(
+                self.pressure?,
+                sea_level,
+                self.temperature?,
+            ))
+        }
+
+        /// See [`sea_level_pressure_f32`]
+        pub fn sea_level_pressure(&self, elevation: f32) -> Option<f32> {
+            Some(sea_level_pressure_f32(self.pressure?, elevation))
+        }
+
+        /// See [`pressure_altitude_f32`]
+        pub fn pressure_altitude(&self) -> Option<f32> {
+            Some(pressure_altitude_f32(self.pressure?))
+        }
+
+        /// See [`flight_level_f32`]
+        pub fn flight_level(&self) -> Option<f32> {
+            Some(flight_level_f32(self.pressure?))
+        }
+    }
+}
+#[cfg(feature = "float")]
+pub use float::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pa(pa: f32) -> I24F8 {
+        I24F8::from_bits((pa * 256.0) as i32)
+    }
+
+    fn m(m: f32) -> I22F10 {
+        I22F10::from_bits((m * 1024.0) as i32)
+    }
+
+    #[test]
+    fn test_altitude() {
+        // Standard atmosphere pressure at 1000 m and 5000 m
+        let standard = altitude(pa(89874.57), STANDARD_SEA_LEVEL_PRESSURE).unwrap();
+        assert!((standard.to_f32() - 1000.0).abs() < 0.1);
+        assert!((pressure_altitude(pa(54019.91)).to_f32() - 5000.0).abs() < 0.1);
+        assert!(
+            pressure_altitude(STANDARD_SEA_LEVEL_PRESSURE)
+                .to_f32()
+                .abs()
+                < 0.01
+        );
+        // Below sea level
+        assert!((pressure_altitude(pa(107477.63)).to_f32() + 500.0).abs() < 0.1);
+
+        // Same ratio to reference pressure gives same altitude
+        let qnh = pa(100000.0);
+        let pressure = pa(89874.57 * 100000.0 / 101325.0);
+        assert!((altitude(pressure, qnh).unwrap().to_f32() - 1000.0).abs() < 0.1);
+
+        assert_eq!(None, altitude(pressure, pa(0.0)));
+        assert_eq!(None, altitude(pressure, pa(-1.0)));
+    }
+
+    #[test]
+    fn test_stratosphere() {
+        // Standard atmosphere pressure at 15000 m
+        assert!((pressure_altitude(pa(12044.57)).to_f32() - 15000.0).abs() < 0.5);
+        let qnh = sea_level_pressure(pa(12044.57), m(15000.0));
+        assert!((qnh.to_f32() - 101325.0).abs() < 5.0);
+
+        // Clamped above 20000 m
+        assert_eq!(20000, pressure_altitude(pa(3000.0)).get_int());
+        assert_eq!(20000, pressure_altitude(pa(0.0)).get_int());
+        assert_eq!(656, flight_level(pa(3000.0)));
+        for elevation in (16000..=30000).step_by(10) {
+            assert!(sea_level_pressure(pa(5000.0), m(elevation as f32)).to_bits() > 0);
+        }
+    }
+
+    #[test]
+    fn test_altitude_temp_corrected() {
+        let pressure = pa(89874.57);
+        // Standard atmosphere temperature at 1000 m
+        let corrected =
+            altitude_temp_corrected(pressure, STANDARD_SEA_LEVEL_PRESSURE, m(8.5)).unwrap();
+        assert!((corrected.to_f32() - 1000.0).abs() < 0.1);
+
+        // Warmer air is less dense, so same pressure drop takes more height
+        let corrected =
+            altitude_temp_corrected(pressure, STANDARD_SEA_LEVEL_PRESSURE, m(28.5)).unwrap();
+        assert!((corrected.to_f32() - 1071.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn test_sea_level_pressure() {
+        let qnh = sea_level_pressure(pa(89874.57), m(1000.0));
+        assert!((qnh.to_f32() - 101325.0).abs() < 1.0);
+
+        // Station pressure at 432 m with 998 hPa QNH
+        let pressure = pa(94793.31);
+        let qnh = sea_level_pressure(pressure, m(432.0));
+        assert!((qnh.to_f32() - 99800.0).abs() < 2.0);
+        assert!((altitude(pressure, qnh).unwrap().to_f32() - 432.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_flight_level() {
+        assert_eq!(0, flight_level(STANDARD_SEA_LEVEL_PRESSURE));
+        // 3000 m is 9842.5 ft
+        assert_eq!(98, flight_level(pa(70108.55)));
+        assert_eq!(-3, flight_level(pa(102414.0)));
+    }
+
+    #[test]
+    fn test_measurement_altitude() {
+        let measurement = Measurement {
+            temperature: Some(m(8.5)),
+            pressure: Some(pa(89874.57)),
+            humidity: None,
+            adc_t: 0,
+            adc_p: 0,
+            adc_h: 0,
+            t_fine: 0,
+        };
+        assert!((measurement.pressure_altitude().unwrap().to_f32() - 1000.0).abs() < 0.1);
+        assert_eq!(Some(33), measurement.flight_level());
+
+        let measurement = Measurement {
+            pressure: None,
+            ..measurement
+        };
+        assert_eq!(None, measurement.altitude(STANDARD_SEA_LEVEL_PRESSURE));
+        assert_eq!(
+            None,
+            measurement.altitude_temp_corrected(STANDARD_SEA_LEVEL_PRESSURE)
+        );
+    }
+
+    #[cfg(feature = "float")]
+    #[test]
+    fn test_float_matches_integer() {
+        // Including stratosphere and clamped range above 20000 m and below -1000 m
+        for pressure in (1000..=120000).step_by(250) {
+            let pressure = pressure as f32;
+            let float = pressure_altitude_f32(pressure);
+            let fixed = pressure_altitude(pa(pressure)).to_f32();
+            assert!(
+                (float - fixed).abs() < 0.5,
+                "{pressure}: {float} != {fixed}"
+            );
+            // Integer flight level is rounded
+            let float = flight_level_f32(pressure);
+            let fixed = flight_level(pa(pressure)) as f32;
+            assert!(
+                (float - fixed).abs() < 0.6,
+                "{pressure}: {float} != {fixed}"
+            );
+
+            let float = altitude_temp_corrected_f32(pressure, 100000.0, -10.0);
+            let fixed = altitude_temp_corrected(pa(pressure), pa(100000.0), m(-10.0))
+                .unwrap()
+                .to_f32();
+            assert!(
+                (float - fixed).abs() < 0.5,
+                "{pressure}: {float} != {fixed}"
+            );
+        }
+
+        for elevation in (-2000..=25000).step_by(100) {
+            let elevation = elevation as f32;
+            let float = sea_level_pressure_f32(50000.0, elevation);
+            let fixed = sea_level_pressure(pa(50000.0), m(elevation)).to_f32();
+            assert!(
+                (float - fixed).abs() / float < 1e-5,
+                "{elevation}: {float} != {fixed}"
+            );
+        }
+
+        let qnh = sea_level_pressure_f32(89874.57, 1000.0);
+        assert!((qnh - 101325.0).abs() < 1.0);
+        assert!((flight_level_f32(70108.55) - 98.425).abs() < 0.01);
+        assert!(altitude_f32(50000.0, 0.0).is_nan());
+    }
+}
@@ -0,0 +1,560 @@
+use crate::submodules::registers::{Id, Mode, RegAddr, SPI_READ_FLAG};
+use crate::submodules::shared::{
+    probed_chip, Access, Init, Measure, ReadCalib, ReadChip, ReadRaw, Reconfigure, Sequence,
+    SetMode, SoftReset, Step, MAX_READ_LEN,
+};
+use crate::{Address, Bme280Config, Bme280Error, CalibData, ChipVariant, Compensator, Measurement};
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::i2c::I2c;
+use embedded_hal_async::spi::{Operation, SpiDevice};
+
+#[allow(async_fn_in_trait)]
+pub trait AsyncRegRead {
+    type Error;
+
+    async fn reg_read(
+        &mut self,
+        dev_addr: u8,
+        reg_addr: u8,
+        buf: &mut [u8],
+    ) -> Result<(), Self::Error>;
+}
+
+#[allow(async_fn_in_trait)]
+pub trait AsyncRegWrite {
+    type Error;
+
+    async fn reg_write(&mut self, dev_addr: u8, reg_addr: u8, data: u8) -> Result<(), Self::Error>;
+}
+
+/// I2C transport over embedded-hal-async `I2c`
+pub struct I2cInterface<I2C>(pub I2C);
+
+impl<I2C: I2c> AsyncRegRead for I2cInterface<I2C> {
+    type Error = I2C::Error;
+
+    async fn reg_read(
+        &mut self,
+        dev_addr: u8,
+        reg_addr: u8,
+        buf: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        self.0.write_read(dev_addr, &[reg_addr], buf).await
+    }
+}
+
+impl<I2C: I2c> AsyncRegWrite for I2cInterface<I2C> {
+    type Error = I2C::Error;
+
+    async fn reg_write(&mut self, dev_addr: u8, reg_addr: u8, data: u8) -> Result<(), Self::Error> {
+        self.0.write(dev_addr, &[reg_addr, data]).await
+    }
+}
+
+/// Async version of [`crate::submodules::i2c::probe`]
+pub async fn probe<I2C: I2c>(i2c: &mut I2C) -> [(Address, Option<ChipVariant>); 2] {
+    let mut found = Address::PROBED.map(|address| (address, None));
+    for (address, chip) in found.iter_mut() {
+        let mut buf = [0];
+        let id_read = i2c
+            .write_read((*address).into(), &[Id::START_ADDR], &mut buf)
+            .await
+            .map(|()| buf[0]);
+        *chip = probed_chip(id_read);
+    }
+    found
+}
+
+/// 4-wire SPI transport over embedded-hal-async `SpiDevice`. Device address passed to the
+/// driver is ignored
+pub struct SpiDeviceInterface<SPI>(pub SPI);
+
+impl<SPI: SpiDevice> AsyncRegRead for SpiDeviceInterface<SPI> {
+    type Error = SPI::Error;
+
+    async fn reg_read(
+        &mut self,
+        _dev_addr: u8,
+        reg_addr: u8,
+        buf: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        self.0
+            .transaction(&mut [
+                Operation::Write(&[reg_addr | SPI_READ_FLAG]),
+                Operation::Read(buf),
+            ])
+            .await
+    }
+}
+
+impl<SPI: SpiDevice> AsyncRegWrite for SpiDeviceInterface<SPI> {
+    type Error = SPI::Error;
+
+    async fn reg_write(
+        &mut self,
+        _dev_addr: u8,
+        reg_addr: u8,
+        data: u8,
+    ) -> Result<(), Self::Error> {
+        self.0.write(&[reg_addr & !SPI_READ_FLAG, data]).await
+    }
+}
+
+/// Async twin of [`crate::Bme280`], waiting for conversions without blocking the executor
+pub struct AsyncBme280<InterfaceT, DelayT> {
+    interface: InterfaceT,
+    dev_addr: u8,
+    chip: ChipVariant,
+    calib_data: CalibData,
+    delay: DelayT,
+    config: Bme280Config,
+}
+
+impl<InterfaceT, InterfaceE, DelayT> AsyncBme280<InterfaceT, DelayT>
+where
+    InterfaceT: AsyncRegRead<Error = InterfaceE> + AsyncRegWrite<Error = InterfaceE>,
+    DelayT: DelayNs,
+{
+    pub async fn init(
+        interface: InterfaceT,
+        dev_addr: impl Into<u8>,
+        delay: DelayT,
+        config: Bme280Config,
+    ) -> Result<Self, Bme280Error<InterfaceE>> {
+        Self::init_inner(interface, dev_addr.into(), delay, config, None).await
+    }
+
+    /// Same as [`Self::init`], but uses `calib_data` (e.g. restored from flash) instead of
+    /// reading it from the sensor
+    pub async fn init_with_calib(
+        interface: InterfaceT,
+        dev_addr: impl Into<u8>,
+        delay: DelayT,
+        config: Bme280Config,
+        calib_data: CalibData,
+    ) -> Result<Self, Bme280Error<InterfaceE>> {
+        Self::init_inner(interface, dev_addr.into(), delay, config, Some(calib_data)).await
+    }
+
+    /// Give back interface and delay. Sensor keeps its current mode
+    pub fn free(self) -> (InterfaceT, DelayT) {
+        (self.interface, self.delay)
+    }
+
+    async fn init_inner(
+        mut interface: InterfaceT,
+        dev_addr: u8,
+        mut delay: DelayT,
+        config: Bme280Config,
+        calib_data: Option<CalibData>,
+    ) -> Result<Self, Bme280Error<InterfaceE>> {
+        let init = Init::new(config, calib_data, false)?;
+        let (chip, calib_data, config) =
+            Self::run_on(&mut interface, dev_addr, Some(&mut delay), init).await?;
+
+        Ok(Self {
+            calib_data,
+            dev_addr,
+            chip,
+            interface,
+            delay,
+            config,
+        })
+    }
+
+    // Async version of `Bme280::run_on`
+    async fn run_on<S: Sequence<InterfaceE>>(
+        interface: &mut InterfaceT,
+        dev_addr: u8,
+        mut delay: Option<&mut DelayT>,
+        mut seq: S,
+    ) -> Result<S::Output, Bme280Error<InterfaceE>> {
+        let mut buf = [0; MAX_READ_LEN];
+        let mut last = Ok(0);
+        loop {
+            let access = match seq.step(last.map(|len| &buf[..len]))? {
+                Step::Access(access) => access,
+                Step::Done(output) => return Ok(output),
+            };
+            last = match access {
+                Access::Read(reg_addr, len) => interface
+                    .reg_read(dev_addr, reg_addr, &mut buf[..len])
+                    .await
+                    .map(|()| len),
+                Access::Write(reg_addr, data) => interface
+                    .reg_write(dev_addr, reg_addr, data)
+                    .await
+                    .map(|()| 0),
+                Access::DelayMs(ms) => {
+                    if let Some(delay) = delay.as_deref_mut() {
+                        delay.delay_ms(ms).await;
+                    }
+                    Ok(0)
+                }
+            }
+            .map_err(Bme280Error::Inteface);
+        }
+    }
+
+    async fn run<S: Sequence<InterfaceE>>(
+        &mut self,
+        seq: S,
+    ) -> Result<S::Output, Bme280Error<InterfaceE>> {
+        Self::run_on(
+            &mut self.interface,
+            self.dev_addr,
+            Some(&mut self.delay),
+            seq,
+        )
+        .await
+    }
+
+    /// Apply new configuration at runtime. Sensor is put to sleep first, since config
+    /// writes may be ignored in normal mode, and restarted if `config` asks for normal mode.
+    /// On failure previous configuration is kept and applied again
+    pub async fn reconfigure(
+        &mut self,
+        config: Bme280Config,
+    ) -> Result<(), Bme280Error<InterfaceE>> {
+        let reconfigure = Reconfigure::new(config, self.config, self.chip, false)?;
+        self.config = self.run(reconfigure).await?;
+        Ok(())
+    }
+
+    /// Reads and validates calibration without initializing the driver, so reads failing with
+    /// [`Bme280Error::InvalidCalibration`] can be retried before [`Self::init_with_calib`]
+    pub async fn read_calibration(
+        interface: &mut InterfaceT,
+        dev_addr: impl Into<u8>,
+    ) -> Result<CalibData, Bme280Error<InterfaceE>> {
+        let dev_addr = dev_addr.into();
+        let chip = Self::run_on(interface, dev_addr, None, ReadChip::new()).await?;
+        Self::run_on(interface, dev_addr, None, ReadCalib::new(chip)).await
+    }
+
+    /// Reads calibration again, current one is kept if read fails
+    pub async fn reload_calib(&mut self) -> Result<(), Bme280Error<InterfaceE>> {
+        self.calib_data = self.run(ReadCalib::new(self.chip)).await?;
+        Ok(())
+    }
+
+    /// Reset sensor, then read calibration and apply current config again. Calibration is
+    /// kept if any step fails
+    pub async fn soft_reset(&mut self) -> Result<(), Bme280Error<InterfaceE>> {
+        self.calib_data = self
+            .run(SoftReset::new(self.config, self.chip, false))
+            .await?;
+        Ok(())
+    }
+
+    /// Trigger forced conversion and wait for its result
+    pub async fn measure(&mut self) -> Result<Measurement, Bme280Error<InterfaceE>> {
+        let raw_measures = self.run(Measure::new(&self.config)).await?;
+
+        Ok(self.compensator().compensate_raw(&raw_measures))
+    }
+
+    /// Switch sensor to normal mode, in which it cycles between conversions and
+    /// standby periods on its own
+    pub async fn start_normal(&mut self) -> Result<(), Bme280Error<InterfaceE>> {
+        self.run(SetMode::new(Mode::Normal)).await
+    }
+
+    /// Read the most recent conversion without triggering a new one
+    pub async fn read_latest(&mut self) -> Result<Measurement, Bme280Error<InterfaceE>> {
+        let raw_measures = self.run(ReadRaw::new()).await?;
+
+        Ok(self.compensator().compensate_raw(&raw_measures))
+    }
+
+    pub fn get_calib(&self) -> &CalibData {
+        &self.calib_data
+    }
+
+    pub fn chip(&self) -> ChipVariant {
+        self.chip
+    }
+
+    /// Compensator matching this sensor, e.g. to process logged raw readings later
+    pub fn compensator(&self) -> Compensator {
+        Compensator {
+            calib_data: self.calib_data,
+            chip: self.chip,
+            trim: self.config.trim,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::submodules::registers::{CtrlMeas, RawMeasures, Status};
+    use crate::tests::{mock_regs, MOCK_CALIB_DATA};
+    use crate::{ConfigError, Oversampling};
+    use core::future::Future;
+    use core::pin::pin;
+    use core::task::{Context, Poll, Waker};
+    use embedded_hal_async::i2c;
+
+    // Mock bus never returns Pending, so single poll completes any driver future
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut cx = Context::from_waker(Waker::noop());
+        match pin!(future).poll(&mut cx) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("future is not ready"),
+        }
+    }
+
+    struct MockBus {
+        regs: [u8; 256],
+        // I2C address sensor answers at
+        address: u8,
+        // Register, writes to which are ignored
+        read_only: Option<u8>,
+        delayed_ns: u64,
+    }
+
+    impl MockBus {
+        fn new() -> Self {
+            Self {
+                regs: mock_regs(),
+                address: 0x76,
+                read_only: None,
+                delayed_ns: 0,
+            }
+        }
+    }
+
+    impl i2c::ErrorType for MockBus {
+        type Error = i2c::ErrorKind;
+    }
+
+    impl I2c for MockBus {
+        async fn transaction(
+            &mut self,
+            address: u8,
+            operations: &mut [i2c::Operation<'_>],
+        ) -> Result<(), Self::Error> {
+            if address != self.address {
+                return Err(i2c::ErrorKind::NoAcknowledge(
+                    i2c::NoAcknowledgeSource::Address,
+                ));
+            }
+            let mut addr = 0u8;
+            for op in operations {
+                match op {
+                    i2c::Operation::Write([first, data @ ..]) => {
+                        addr = *first;
+                        for &word in data.iter() {
+                            if self.read_only != Some(addr) {
+                                self.regs[addr as usize] = word;
+                            }
+                            addr = addr.wrapping_add(1);
+                        }
+                    }
+                    i2c::Operation::Read(words) => {
+                        for word in words.iter_mut() {
+                            *word = self.regs[addr as usize];
+                            addr = addr.wrapping_add(1);
+                        }
+                    }
+                    _ => unimplemented!(),
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl DelayNs for MockBus {
+        async fn delay_ns(&mut self, ns: u32) {
+            self.delayed_ns += ns as u64;
+        }
+    }
+
+    fn init(bus: MockBus, config: Bme280Config) -> AsyncBme280<I2cInterface<MockBus>, MockBus> {
+        block_on(AsyncBme280::init(
+            I2cInterface(bus),
+            0x76,
+            MockBus::new(),
+            config,
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_async_measure() {
+        let config = Bme280Config {
+            hum_oversampling: Oversampling::X1,
+            temp_oversampling: Oversampling::X1,
+            press_oversampling: Oversampling::X1,
+            ..Default::default()
+        };
+        let mut bme280 = init(MockBus::new(), config);
+        assert_eq!(28765, bme280.get_calib().dig_t1);
+
+        let measurement = block_on(bme280.measure()).unwrap();
+        assert_eq!(21, measurement.temperature.unwrap().get_int());
+        assert_eq!(
+            Compensator::new(bme280.calib_data)
+                .compensate_raw(&RawMeasures(mock_regs()[0xF7..=0xFE].try_into().unwrap())),
+            measurement
+        );
+        assert_eq!(
+            0b00100101,
+            bme280.interface.0.regs[CtrlMeas::START_ADDR as usize]
+        );
+    }
+
+    #[test]
+    fn test_async_measurement_timeout() {
+        let mut bme280 = init(MockBus::new(), Default::default());
+
+        // Floating bus
+        bme280.interface.0.regs[Status::START_ADDR as usize] = 0xFF;
+        bme280.delay.delayed_ns = 0;
+        assert!(matches!(
+            block_on(bme280.measure()),
+            Err(Bme280Error::Timeout)
+        ));
+        // Gave up after maximum conversion time, 9.3 ms for all channels ×1
+        assert_eq!(10_000_000, bme280.delay.delayed_ns);
+    }
+
+    #[test]
+    fn test_async_nvm_copy_timeout() {
+        let mut bus = MockBus::new();
+        bus.regs[Status::START_ADDR as usize] = 0b00000001;
+        assert!(matches!(
+            block_on(AsyncBme280::init(
+                I2cInterface(bus),
+                0x76,
+                MockBus::new(),
+                Default::default()
+            )),
+            Err(Bme280Error::NvmCopyTimeout)
+        ));
+    }
+
+    #[test]
+    fn test_async_init_with_calib() {
+        let mut calib_data = *MOCK_CALIB_DATA;
+        calib_data.dig_t1 += 1;
+        let bme280 = block_on(AsyncBme280::init_with_calib(
+            I2cInterface(MockBus::new()),
+            0x76,
+            MockBus::new(),
+            Default::default(),
+            calib_data,
+        ))
+        .unwrap();
+        assert_eq!(&calib_data, bme280.get_calib());
+
+        assert!(matches!(
+            block_on(AsyncBme280::init_with_calib(
+                I2cInterface(MockBus::new()),
+                0x76,
+                MockBus::new(),
+                Default::default(),
+                CalibData::from_bytes(&[0xFF; CalibData::BYTES_LEN]),
+            )),
+            Err(Bme280Error::InvalidCalibration)
+        ));
+    }
+
+    #[test]
+    fn test_async_read_calibration() {
+        let mut interface = I2cInterface(MockBus::new());
+        let calib_data = block_on(AsyncBme280::<_, MockBus>::read_calibration(
+            &mut interface,
+            0x76,
+        ))
+        .unwrap();
+        assert_eq!(*MOCK_CALIB_DATA, calib_data);
+
+        interface.0.regs[0x88..=0xA1].fill(0xFF);
+        assert!(matches!(
+            block_on(AsyncBme280::<_, MockBus>::read_calibration(
+                &mut interface,
+                0x76
+            )),
+            Err(Bme280Error::InvalidCalibration)
+        ));
+    }
+
+    #[test]
+    fn test_async_reconfigure() {
+        let mut bme280 = init(MockBus::new(), Default::default());
+
+        block_on(bme280.reconfigure(Bme280Config::indoor_navigation())).unwrap();
+        let regs = &bme280.interface.0.regs;
+        assert_eq!(0b00000001, regs[0xF2]);
+        assert_eq!(0b01010111, regs[CtrlMeas::START_ADDR as usize]);
+        assert_eq!(0b00010000, regs[0xF5]);
+
+        assert!(matches!(
+            block_on(bme280.reconfigure(Bme280Config {
+                temp_oversampling: Oversampling::ModuleDisabled,
+                ..Default::default()
+            })),
+            Err(Bme280Error::InvalidConfig(ConfigError::TemperatureDisabled))
+        ));
+
+        bme280.interface.0.read_only = Some(0xF5);
+        assert!(matches!(
+            block_on(bme280.reconfigure(Default::default())),
+            Err(Bme280Error::ConfigNotApplied)
+        ));
+        assert_eq!(Bme280Config::indoor_navigation(), bme280.config);
+        assert_eq!(
+            Bme280Config::indoor_navigation().measurement_time(),
+            bme280.config.measurement_time()
+        );
+        // and applied again
+        let regs = &bme280.interface.0.regs;
+        assert_eq!(0b00000001, regs[0xF2]);
+        assert_eq!(0b01010111, regs[CtrlMeas::START_ADDR as usize]);
+    }
+
+    #[test]
+    fn test_async_soft_reset() {
+        let mut bme280 = init(MockBus::new(), Bme280Config::indoor_navigation());
+
+        // Reset clears control registers
+        bme280.interface.0.regs[CtrlMeas::START_ADDR as usize] = 0;
+        bme280.interface.0.regs[0xF5] = 0;
+        block_on(bme280.soft_reset()).unwrap();
+        assert_eq!(
+            0b01010111,
+            bme280.interface.0.regs[CtrlMeas::START_ADDR as usize]
+        );
+        assert_eq!(0b00010000, bme280.interface.0.regs[0xF5]);
+
+        bme280.interface.0.regs[Status::START_ADDR as usize] = 0b00000001;
+        assert!(matches!(
+            block_on(bme280.soft_reset()),
+            Err(Bme280Error::NvmCopyTimeout)
+        ));
+    }
+
+    #[test]
+    fn test_async_probe() {
+        let mut bus = MockBus::new();
+        bus.address = 0x77;
+        assert_eq!(
+            [
+                (Address::Primary, None),
+                (Address::Secondary, Some(ChipVariant::Bme280))
+            ],
+            block_on(probe(&mut bus))
+        );
+
+        let bme280 = block_on(AsyncBme280::init(
+            I2cInterface(bus),
+            Address::Secondary,
+            MockBus::new(),
+            Default::default(),
+        ))
+        .unwrap();
+        assert_eq!(0x77, bme280.dev_addr);
+    }
+}
@@ -0,0 +1,229 @@
+use crate::submodules::registers::{Id, RegAddr, SPI_READ_FLAG};
+use crate::submodules::shared::probed_chip;
+use crate::{Address, ChipVariant, RegRead, RegWrite};
+use embedded_hal::blocking::delay::DelayMs;
+use embedded_hal_1::delay::DelayNs;
+use embedded_hal_1::i2c::I2c;
+use embedded_hal_1::spi::{Operation, SpiDevice};
+
+/// I2C transport over embedded-hal 1.0 `I2c`
+pub struct I2cInterface<I2C>(pub I2C);
+
+impl<I2C: I2c> RegRead for I2cInterface<I2C> {
+    type Error = I2C::Error;
+
+    fn reg_read(&mut self, dev_addr: u8, reg_addr: u8, buf: &mut [u8]) -> Result<(), Self::Error> {
+        self.0.write_read(dev_addr, &[reg_addr], buf)
+    }
+}
+
+impl<I2C: I2c> RegWrite for I2cInterface<I2C> {
+    type Error = I2C::Error;
+
+    fn reg_write(&mut self, dev_addr: u8, reg_addr: u8, data: u8) -> Result<(), Self::Error> {
+        self.0.write(dev_addr, &[reg_addr, data])
+    }
+}
+
+/// Same as [`crate::submodules::i2c::probe`], over embedded-hal 1.0 `I2c`
+pub fn probe<I2C: I2c>(i2c: &mut I2C) -> [(Address, Option<ChipVariant>); 2] {
+    Address::PROBED.map(|address| {
+        let mut buf = [0];
+        let id_read = i2c
+            .write_read(address.into(), &[Id::START_ADDR], &mut buf)
+            .map(|()| buf[0]);
+        (address, probed_chip(id_read))
+    })
+}
+
+/// 4-wire SPI transport over embedded-hal 1.0 `SpiDevice`, which manages chip select itself.
+/// Device address passed to the driver is ignored
+pub struct SpiDeviceInterface<SPI>(pub SPI);
+
+impl<SPI: SpiDevice> RegRead for SpiDeviceInterface<SPI> {
+    type Error = SPI::Error;
+
+    fn reg_read(&mut self, _dev_addr: u8, reg_addr: u8, buf: &mut [u8]) -> Result<(), Self::Error> {
+        self.0.transaction(&mut [
+            Operation::Write(&[reg_addr | SPI_READ_FLAG]),
+            Operation::Read(buf),
+        ])
+    }
+}
+
+impl<SPI: SpiDevice> RegWrite for SpiDeviceInterface<SPI> {
+    type Error = SPI::Error;
+
+    fn reg_write(&mut self, _dev_addr: u8, reg_addr: u8, data: u8) -> Result<(), Self::Error> {
+        self.0.write(&[reg_addr & !SPI_READ_FLAG, data])
+    }
+}
+
+/// Makes embedded-hal 1.0 `DelayNs` usable as driver delay
+pub struct Delay<D>(pub D);
+
+impl<D: DelayNs> DelayMs<u16> for Delay<D> {
+    fn delay_ms(&mut self, ms: u16) {
+        self.0.delay_ms(ms as u32);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::mock_regs;
+    use crate::{Bme280, Bme280Config, Oversampling};
+    use core::convert::Infallible;
+    use embedded_hal_1::i2c;
+    use embedded_hal_1::spi;
+
+    struct MockBus {
+        regs: [u8; 256],
+        delayed_ns: u64,
+    }
+
+    impl MockBus {
+        fn new() -> Self {
+            Self {
+                regs: mock_regs(),
+                delayed_ns: 0,
+            }
+        }
+
+        // First written byte selects register, following ones are written to consecutive
+        // registers. Reads continue from selected register
+        fn write(&mut self, addr: &mut u8, words: &[u8]) {
+            let (first, data) = words.split_first().unwrap();
+            *addr = *first;
+            for &word in data {
+                self.regs[*addr as usize] = word;
+                *addr = addr.wrapping_add(1);
+            }
+        }
+
+        fn read(&mut self, addr: &mut u8, words: &mut [u8]) {
+            for word in words.iter_mut() {
+                *word = self.regs[*addr as usize];
+                *addr = addr.wrapping_add(1);
+            }
+        }
+    }
+
+    impl i2c::ErrorType for MockBus {
+        type Error = i2c::ErrorKind;
+    }
+
+    impl I2c for MockBus {
+        fn transaction(
+            &mut self,
+            address: u8,
+            operations: &mut [i2c::Operation<'_>],
+        ) -> Result<(), Self::Error> {
+            if address != 0x76 {
+                return Err(i2c::ErrorKind::NoAcknowledge(
+                    i2c::NoAcknowledgeSource::Address,
+                ));
+            }
+            let mut addr = 0;
+            for op in operations {
+                match op {
+                    i2c::Operation::Write(words) => self.write(&mut addr, words),
+                    i2c::Operation::Read(words) => self.read(&mut addr, words),
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl spi::ErrorType for MockBus {
+        type Error = Infallible;
+    }
+
+    impl SpiDevice for MockBus {
+        fn transaction(
+            &mut self,
+            operations: &mut [spi::Operation<'_, u8>],
+        ) -> Result<(), Self::Error> {
+            let mut addr = 0;
+            for op in operations {
+                match op {
+                    spi::Operation::Write([ctrl, data @ ..]) => {
+                        // Restore bit 7 dropped in SPI mode
+                        addr = *ctrl | SPI_READ_FLAG;
+                        if *ctrl & SPI_READ_FLAG == 0 {
+                            for &word in data.iter() {
+                                self.regs[addr as usize] = word;
+                            }
+                        }
+                    }
+                    spi::Operation::Read(words) => self.read(&mut addr, words),
+                    _ => unimplemented!(),
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl DelayNs for MockBus {
+        fn delay_ns(&mut self, ns: u32) {
+            self.delayed_ns += ns as u64;
+        }
+    }
+
+    fn config() -> Bme280Config {
+        Bme280Config {
+            hum_oversampling: Oversampling::X1,
+            temp_oversampling: Oversampling::X1,
+            press_oversampling: Oversampling::X1,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_init_over_i2c() {
+        let mut bme280 = Bme280::init(
+            I2cInterface(MockBus::new()),
+            0x76,
+            Delay(MockBus::new()),
+            config(),
+        )
+        .unwrap();
+        assert_eq!(28765, bme280.get_calib().dig_t1);
+
+        let measurement = bme280.do_measurement().unwrap();
+        assert_eq!(21, measurement.temperature.unwrap().get_int());
+    }
+
+    #[test]
+    fn test_init_over_spi_device() {
+        let mut bme280 = Bme280::init(
+            SpiDeviceInterface(MockBus::new()),
+            0,
+            Delay(MockBus::new()),
+            config(),
+        )
+        .unwrap();
+        assert_eq!(293, bme280.get_calib().dig_h4);
+
+        let measurement = bme280.do_measurement().unwrap();
+        assert_eq!(21, measurement.temperature.unwrap().get_int());
+    }
+
+    #[test]
+    fn test_probe() {
+        assert_eq!(
+            [
+                (Address::Primary, Some(ChipVariant::Bme280)),
+                (Address::Secondary, None)
+            ],
+            probe(&mut MockBus::new())
+        );
+    }
+
+    #[test]
+    fn test_delay() {
+        let mut delay = Delay(MockBus::new());
+        DelayMs::delay_ms(&mut delay, 10);
+        assert_eq!(10_000_000, delay.0.delayed_ns);
+    }
+}
@@ -0,0 +1,208 @@
+use crate::{CalibData, Compensator, Formulas, I22F10, I24F8};
+
+/// Measurement in °C, Pa and %RH, `None` for skipped channels and channels compensated with
+/// skipped temperature
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeasurementF32 {
+    pub temperature: Option<f32>,
+    pub pressure: Option<f32>,
+    pub humidity: Option<f32>,
+}
+
+// Floating point formulas from BME 280 datasheet chapter 8.1, generated for both f32 and f64
+macro_rules! float_compensation {
+    ($float:ty, $compensate_t:ident, $compensate_p:ident, $compensate_h:ident) => {
+        impl CalibData {
+            /// Returns `(t_fine, temperature)`, temperature in °C
+            pub fn $compensate_t(&self, adc_t: u32) -> ($float, $float) {
+                let adc_t = adc_t as $float;
+                let dig_t1 = self.dig_t1 as $float;
+                let var1 = (adc_t / 16384.0 - dig_t1 / 1024.0) * self.dig_t2 as $float;
+                let var2 = (adc_t / 131072.0 - dig_t1 / 8192.0)
+                    * (adc_t / 131072.0 - dig_t1 / 8192.0)
+                    * self.dig_t3 as $float;
+                let t_fine = var1 + var2;
+
+                (t_fine, t_fine / 5120.0)
+            }
+
+            /// Pressure in Pa
+            pub fn $compensate_p(&self, t_fine: $float, adc_p: u32) -> $float {
+                let mut var1 = t_fine / 2.0 - 64000.0;
+                let mut var2 = var1 * var1 * self.dig_p6 as $float / 32768.0;
+                var2 += var1 * self.dig_p5 as $float * 2.0;
+                var2 = var2 / 4.0 + self.dig_p4 as $float * 65536.0;
+                var1 = (self.dig_p3 as $float * var1 * var1 / 524288.0
+                    + self.dig_p2 as $float * var1)
+                    / 524288.0;
+                var1 = (1.0 + var1 / 32768.0) * self.dig_p1 as $float;
+                if var1 == 0.0 {
+                    return 0.0; // avoid exception caused by division by zero
+                }
+                let mut p = 1048576.0 - adc_p as $float;
+                p = (p - var2 / 4096.0) * 6250.0 / var1;
+                var1 = self.dig_p9 as $float * p * p / 2147483648.0;
+                var2 = p * self.dig_p8 as $float / 32768.0;
+
+                p + (var1 + var2 + self.dig_p7 as $float) / 16.0
+            }
+
+            /// Relative humidity in %
+            pub fn $compensate_h(&self, t_fine: $float, adc_h: u32) -> $float {
+                let mut h = t_fine - 76800.0;
+                h = (adc_h as $float
+                    - (self.dig_h4 as $float * 64.0 + self.dig_h5 as $float / 16384.0 * h))
+                    * (self.dig_h2 as $float / 65536.0
+                        * (1.0
+                            + self.dig_h6 as $float / 67108864.0
+                                * h
+                                * (1.0 + self.dig_h3 as $float / 67108864.0 * h)));
+                h *= 1.0 - self.dig_h1 as $float * h / 524288.0;
+
+                h.clamp(0.0, 100.0)
+            }
+        }
+    };
+}
+
+float_compensation!(f32, compensate_t_f32, compensate_p_f32, compensate_h_f32);
+float_compensation!(f64, compensate_t_f64, compensate_p_f64, compensate_h_f64);
+
+struct FloatFormulas<'a>(&'a CalibData);
+
+impl Formulas for FloatFormulas<'_> {
+    type TFine = f32;
+    type Temperature = f32;
+    type Pressure = f32;
+    type Humidity = f32;
+
+    fn t_fine(&self, adc_t: u32) -> f32 {
+        self.0.compensate_t_f32(adc_t).0
+    }
+
+    fn temperature(t_fine: f32) -> f32 {
+        t_fine / 5120.0
+    }
+
+    fn pressure(&self, t_fine: f32, adc_p: u32) -> f32 {
+        self.0.compensate_p_f32(t_fine, adc_p)
+    }
+
+    fn humidity(&self, t_fine: f32, adc_h: u32) -> f32 {
+        self.0.compensate_h_f32(t_fine, adc_h)
+    }
+
+    fn t_fine_offset(temperature: I22F10) -> f32 {
+        temperature.to_f32() * 5120.0
+    }
+
+    fn pressure_value(pressure: I24F8) -> f32 {
+        pressure.to_f32()
+    }
+
+    fn humidity_value(humidity: I22F10) -> f32 {
+        humidity.to_f32()
+    }
+}
+
+impl Compensator {
+    /// Same as [`Compensator::compensate`], but with floating point formulas
+    pub fn compensate_f32(&self, adc_t: u32, adc_p: u32, adc_h: u32) -> MeasurementF32 {
+        let channels = self.compensate_with(FloatFormulas(&self.calib_data), adc_t, adc_p, adc_h);
+
+        MeasurementF32 {
+            temperature: channels.temperature,
+            pressure: channels.pressure,
+            humidity: channels.humidity,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::submodules::registers::RawMeasures;
+    use crate::tests::MOCK_CALIB_DATA;
+    use crate::{ChipVariant, Compensator, Trim, I22F10};
+
+    #[test]
+    fn test_compensate_t_float() {
+        // Integer compensation gives 21.50 °C and t_fine 110074, with few LSBs lost to
+        // truncation (1 LSB of t_fine is 0.0002 °C)
+        let (t_fine, t) = MOCK_CALIB_DATA.compensate_t_f64(526514);
+        assert!((t_fine - 110074.0).abs() < 5.0);
+        assert!((t - 21.50).abs() < 0.01);
+
+        let (t_fine, t) = MOCK_CALIB_DATA.compensate_t_f32(526514);
+        assert!((t_fine - 110074.0).abs() < 5.0);
+        assert!((t - 21.50).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_compensate_p_float() {
+        // Integer compensation gives 26110518 / 256 Pa
+        let expected = 26110518.0 / 256.0;
+        assert!((MOCK_CALIB_DATA.compensate_p_f64(120188.0, 322858) - expected).abs() < 1.0);
+        assert!((MOCK_CALIB_DATA.compensate_p_f32(120188.0, 322858) - expected as f32).abs() < 2.0);
+    }
+
+    #[test]
+    fn test_compensate_h_float() {
+        // Integer compensation gives 27726 / 1024 %RH
+        let expected = 27726.0 / 1024.0;
+        assert!((MOCK_CALIB_DATA.compensate_h_f64(99523.0, 23549) - expected).abs() < 0.01);
+        assert!((MOCK_CALIB_DATA.compensate_h_f32(99523.0, 23549) - expected as f32).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_compensate_f32() {
+        let mut compensator = Compensator {
+            calib_data: *MOCK_CALIB_DATA,
+            chip: ChipVariant::Bme280,
+            trim: Trim::default(),
+        };
+        let expected = compensator.compensate(526514, 322858, 23549);
+        let measurement = compensator.compensate_f32(526514, 322858, 23549);
+        assert!(
+            (measurement.temperature.unwrap() - expected.temperature.unwrap().to_f32()).abs()
+                < 0.01
+        );
+        assert!((measurement.pressure.unwrap() - expected.pressure.unwrap().to_f32()).abs() < 2.0);
+        assert!((measurement.humidity.unwrap() - expected.humidity.unwrap().to_f32()).abs() < 0.05);
+
+        compensator.trim.temperature_offset = I22F10::new(1, 0);
+        compensator.trim.humidity_gain = I22F10::new(2, 0);
+        let trimmed = compensator.compensate_f32(526514, 322858, 23549);
+        assert!(
+            (trimmed.temperature.unwrap() - measurement.temperature.unwrap() - 1.0).abs() < 0.01
+        );
+        assert!(trimmed.humidity.unwrap() > measurement.humidity.unwrap());
+
+        let skipped = compensator.compensate_f32(RawMeasures::SKIPPED_TEMP_PRESS, 322858, 23549);
+        assert_eq!(skipped.temperature, None);
+        assert_eq!(skipped.pressure, None);
+        assert_eq!(skipped.humidity, None);
+    }
+
+    #[test]
+    fn test_compensate_f32_skipped_like_fixed() {
+        let skipped = RawMeasures::SKIPPED_TEMP_PRESS;
+        for chip in [ChipVariant::Bme280, ChipVariant::Bmp280] {
+            let compensator = Compensator {
+                calib_data: *MOCK_CALIB_DATA,
+                chip,
+                trim: Trim::default(),
+            };
+            for adc_t in [526514, skipped] {
+                for adc_p in [322858, skipped] {
+                    for adc_h in [23549, RawMeasures::SKIPPED_HUM] {
+                        let fixed = compensator.compensate(adc_t, adc_p, adc_h);
+                        let float = compensator.compensate_f32(adc_t, adc_p, adc_h);
+                        assert_eq!(fixed.temperature.is_some(), float.temperature.is_some());
+                        assert_eq!(fixed.pressure.is_some(), float.pressure.is_some());
+                        assert_eq!(fixed.humidity.is_some(), float.humidity.is_some());
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,4 +1,6 @@
-use crate::{RegRead, RegWrite};
+use crate::submodules::registers::{Id, RegAddr};
+use crate::submodules::shared::probed_chip;
+use crate::{Address, ChipVariant, RegRead, RegWrite};
 use embedded_hal::blocking::i2c::{Write, WriteRead};
 
 impl<T: WriteRead> RegRead for T {
@@ -16,3 +18,15 @@ impl<T: Write> RegWrite for T {
         self.write(dev_addr, &[reg_addr, data])
     }
 }
+
+/// Look for sensor on I2C bus by reading `Id` register at each of [`Address::PROBED`]
+/// addresses. Nothing is written, so sensor found keeps its state
+pub fn probe<I2C: WriteRead>(i2c: &mut I2C) -> [(Address, Option<ChipVariant>); 2] {
+    Address::PROBED.map(|address| {
+        let mut buf = [0];
+        let id_read = i2c
+            .write_read(address.into(), &[Id::START_ADDR], &mut buf)
+            .map(|()| buf[0]);
+        (address, probed_chip(id_read))
+    })
+}
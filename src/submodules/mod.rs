@@ -1,2 +1,13 @@
+pub mod altitude;
+#[cfg(feature = "async")]
+pub mod asynch;
+#[cfg(feature = "eh1")]
+pub mod eh1;
+#[cfg(feature = "float")]
+pub mod float;
 pub mod i2c;
+#[cfg(feature = "float")]
+pub mod psychrometrics;
 pub(crate) mod registers;
+pub(crate) mod shared;
+pub mod spi;
@@ -0,0 +1,280 @@
+use crate::{Measurement, MeasurementF32};
+use libm::{expf, logf, sqrtf};
+
+// Magnus formula coefficients over water and ice (Alduchov and Eskridge, 1996), in Pa and °C
+const MAGNUS_WATER: (f32, f32, f32) = (610.94, 17.625, 243.04);
+const MAGNUS_ICE: (f32, f32, f32) = (611.21, 22.587, 273.86);
+// Specific gas constant of water vapor in J/(kg K), and ratio of dry air and water vapor
+// molar masses
+const R_VAPOR: f32 = 461.5;
+const EPSILON: f32 = 0.622;
+
+/// Moist air state, which derived quantities are computed from. Valid for positive humidity
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Psychrometrics {
+    /// Temperature in °C
+    pub temperature: f32,
+    /// Relative humidity in %
+    pub humidity: f32,
+    /// Pressure in Pa
+    pub pressure: f32,
+}
+
+fn magnus((c, a, b): (f32, f32, f32), temperature: f32) -> f32 {
+    c * expf(a * temperature / (temperature + b))
+}
+
+fn magnus_inverse((c, a, b): (f32, f32, f32), vapor_pressure: f32) -> f32 {
+    let gamma = logf(vapor_pressure / c);
+    b * gamma / (a - gamma)
+}
+
+impl Psychrometrics {
+    pub fn new(temperature: f32, humidity: f32, pressure: f32) -> Self {
+        Self {
+            temperature,
+            humidity,
+            pressure,
+        }
+    }
+
+    // Vapor pressure is 0 at 0 % humidity, which derived temperatures take logarithm of
+    fn measured(temperature: f32, humidity: f32, pressure: f32) -> Option<Self> {
+        (humidity > 0.0).then(|| Self::new(temperature, humidity, pressure))
+    }
+
+    /// Saturation vapor pressure over water in Pa
+    pub fn saturation_vapor_pressure(&self) -> f32 {
+        magnus(MAGNUS_WATER, self.temperature)
+    }
+
+    /// Partial pressure of water vapor in Pa
+    pub fn vapor_pressure(&self) -> f32 {
+        self.humidity / 100.0 * self.saturation_vapor_pressure()
+    }
+
+    /// Temperature in °C at which vapor condenses to water
+    pub fn dew_point(&self) -> f32 {
+        magnus_inverse(MAGNUS_WATER, self.vapor_pressure())
+    }
+
+    /// Temperature in °C at which vapor deposits as ice, below dew point when it is under 0 °C
+    pub fn frost_point(&self) -> f32 {
+        magnus_inverse(MAGNUS_ICE, self.vapor_pressure())
+    }
+
+    /// Mass of water vapor per volume of air in g/m³
+    pub fn absolute_humidity(&self) -> f32 {
+        self.vapor_pressure() / (R_VAPOR * (self.temperature + 273.15)) * 1000.0
+    }
+
+    /// Mass of water vapor per mass of dry air in kg/kg
+    pub fn mixing_ratio(&self) -> f32 {
+        let vapor_pressure = self.vapor_pressure();
+        EPSILON * vapor_pressure / (self.pressure - vapor_pressure)
+    }
+
+    /// Mass of water vapor per mass of moist air in kg/kg
+    pub fn specific_humidity(&self) -> f32 {
+        let mixing_ratio = self.mixing_ratio();
+        mixing_ratio / (1.0 + mixing_ratio)
+    }
+
+    /// Temperature in °C of ventilated wet-bulb thermometer, solved from psychrometer equation
+    pub fn wet_bulb(&self) -> f32 {
+        let vapor_pressure = self.vapor_pressure();
+        // Wet bulb is between dew point and dry bulb temperature, bisection converges in
+        // 0.001 °C within 20 iterations
+        let (mut low, mut high) = (self.dew_point(), self.temperature);
+        for _ in 0..20 {
+            let wet_bulb = (low + high) / 2.0;
+            // WMO psychrometer coefficient for ventilated psychrometer
+            let psychrometer = 6.53e-4 * (1.0 + 0.000944 * wet_bulb);
+            let residual = magnus(MAGNUS_WATER, wet_bulb)
+                - psychrometer * self.pressure * (self.temperature - wet_bulb)
+                - vapor_pressure;
+            if residual > 0.0 {
+                high = wet_bulb;
+            } else {
+                low = wet_bulb;
+            }
+        }
+        (low + high) / 2.0
+    }
+
+    /// Specific enthalpy in kJ per kg of dry air, relative to dry air at 0 °C
+    pub fn enthalpy(&self) -> f32 {
+        1.006 * self.temperature + self.mixing_ratio() * (2501.0 + 1.86 * self.temperature)
+    }
+
+    /// Apparent temperature in °C, from US National Weather Service (Rothfusz regression)
+    pub fn heat_index(&self) -> f32 {
+        let t = self.temperature * 9.0 / 5.0 + 32.0;
+        let rh = self.humidity;
+
+        // Steadman's formula, good enough below 80 °F
+        let simple = 0.5 * (t + 61.0 + (t - 68.0) * 1.2 + rh * 0.094);
+        let heat_index = if (simple + t) / 2.0 < 80.0 {
+            simple
+        } else {
+            let mut hi = -42.379 + 2.049_015_2 * t + 10.143_331 * rh
+                - 0.224_755_4 * t * rh
+                - 0.006_837_83 * t * t
+                - 0.054_817_17 * rh * rh
+                + 0.001_228_74 * t * t * rh
+                + 0.000_852_82 * t * rh * rh
+                - 0.000_001_99 * t * t * rh * rh;
+            if rh < 13.0 && (80.0..=112.0).contains(&t) {
+                hi -= (13.0 - rh) / 4.0 * sqrtf((17.0 - (t - 95.0).abs()) / 17.0);
+            } else if rh > 85.0 && (80.0..=87.0).contains(&t) {
+                hi += (rh - 85.0) / 10.0 * (87.0 - t) / 5.0;
+            }
+            hi
+        };
+
+        (heat_index - 32.0) * 5.0 / 9.0
+    }
+
+    /// Apparent temperature in °C, from Environment Canada
+    pub fn humidex(&self) -> f32 {
+        self.temperature + 0.5555 * (self.vapor_pressure() / 100.0 - 10.0)
+    }
+}
+
+impl Measurement {
+    /// `None` if any of temperature, pressure or humidity was not measured, or humidity is 0 %
+    pub fn psychrometrics(&self) -> Option<Psychrometrics> {
+        Psychrometrics::measured(
+            self.temperature?.to_f32(),
+            self.humidity?.to_f32(),
+            self.pressure?.to_f32(),
+        )
+    }
+}
+
+impl MeasurementF32 {
+    /// `None` if any of temperature, pressure or humidity was not measured, or humidity is 0 %
+    pub fn psychrometrics(&self) -> Option<Psychrometrics> {
+        Psychrometrics::measured(self.temperature?, self.humidity?, self.pressure?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{I22F10, I24F8};
+
+    fn assert_near(expected: f32, actual: f32, tolerance: f32) {
+        assert!(
+            (expected - actual).abs() < tolerance,
+            "{expected} != {actual}"
+        );
+    }
+
+    #[test]
+    fn test_vapor_pressure() {
+        // 2339 Pa saturation at 20 °C from steam tables
+        let air = Psychrometrics::new(20.0, 50.0, 101325.0);
+        assert_near(2339.0, air.saturation_vapor_pressure(), 10.0);
+        assert_near(1169.5, air.vapor_pressure(), 5.0);
+    }
+
+    #[test]
+    fn test_dew_and_frost_point() {
+        assert_near(
+            16.7,
+            Psychrometrics::new(25.0, 60.0, 101325.0).dew_point(),
+            0.1,
+        );
+        assert_near(
+            20.0,
+            Psychrometrics::new(20.0, 100.0, 101325.0).dew_point(),
+            0.01,
+        );
+
+        let air = Psychrometrics::new(-5.0, 80.0, 101325.0);
+        assert_near(-7.9, air.dew_point(), 0.1);
+        assert_near(-7.0, air.frost_point(), 0.1);
+    }
+
+    #[test]
+    fn test_moisture_content() {
+        // Psychrometric chart at 25 °C, 50 %RH and sea level
+        let air = Psychrometrics::new(25.0, 50.0, 101325.0);
+        assert_near(11.5, air.absolute_humidity(), 0.1);
+        assert_near(0.00986, air.mixing_ratio(), 0.0001);
+        assert_near(0.00976, air.specific_humidity(), 0.0001);
+        assert_near(50.3, air.enthalpy(), 0.2);
+        assert_near(17.9, air.wet_bulb(), 0.1);
+
+        // Same relative humidity holds more vapor per mass of air at lower pressure
+        let air = Psychrometrics::new(25.0, 50.0, 80000.0);
+        assert!(air.mixing_ratio() > 0.0125);
+        assert_near(17.4, air.wet_bulb(), 0.1);
+    }
+
+    #[test]
+    fn test_apparent_temperature() {
+        // NWS heat index table gives 106 °F at 90 °F and 70 %RH
+        assert_near(
+            41.1,
+            Psychrometrics::new(32.22, 70.0, 101325.0).heat_index(),
+            0.2,
+        );
+        assert_near(
+            19.4,
+            Psychrometrics::new(20.0, 50.0, 101325.0).heat_index(),
+            0.1,
+        );
+
+        assert_near(
+            33.9,
+            Psychrometrics::new(30.0, 40.0, 101325.0).humidex(),
+            0.1,
+        );
+    }
+
+    #[test]
+    fn test_from_measurement() {
+        let measurement = MeasurementF32 {
+            temperature: Some(25.0),
+            pressure: Some(101325.0),
+            humidity: Some(50.0),
+        };
+        let air = measurement.psychrometrics().unwrap();
+        assert_eq!(Psychrometrics::new(25.0, 50.0, 101325.0), air);
+
+        let measurement = MeasurementF32 {
+            humidity: None,
+            ..measurement
+        };
+        assert_eq!(None, measurement.psychrometrics());
+    }
+
+    #[test]
+    fn test_zero_humidity() {
+        // Compensation clamps humidity to 0 %, where dew point would be NaN
+        let measurement = Measurement {
+            temperature: Some(I22F10::new(25, 0)),
+            pressure: Some(I24F8::new(101325, 0)),
+            humidity: Some(I22F10::new(0, 0)),
+            adc_t: 0,
+            adc_p: 0,
+            adc_h: 0,
+            t_fine: 0,
+        };
+        assert_eq!(None, measurement.psychrometrics());
+
+        let measurement = MeasurementF32 {
+            temperature: Some(25.0),
+            pressure: Some(101325.0),
+            humidity: Some(0.0),
+        };
+        assert_eq!(None, measurement.psychrometrics());
+        let measurement = MeasurementF32 {
+            humidity: Some(0.1),
+            ..measurement
+        };
+        assert!(!measurement.psychrometrics().unwrap().dew_point().is_nan());
+    }
+}
@@ -16,6 +16,9 @@ where
     const REG_SIZE: usize = (T::END_ADDR - T::START_ADDR + 1) as usize;
 }
 
+// In SPI mode only 7 bits of register address are used, bit 7 selects read (1) or write (0)
+pub(crate) const SPI_READ_FLAG: u8 = 0x80;
+
 bitfield! {
     pub struct Calib00_25([u8]);
     pub u16, get_dig_t1, _: (16-1), 0;
@@ -49,6 +52,8 @@ impl RegAddr for Id {
 }
 impl Id {
     pub const BME280_STANDARD_ID: u8 = 0x60;
+    pub const BMP280_SAMPLE_IDS: [u8; 2] = [0x56, 0x57];
+    pub const BMP280_MASS_PRODUCTION_ID: u8 = 0x58;
 }
 
 bitfield! {
@@ -67,7 +72,8 @@ impl<T: AsRef<[u8]>> Calib26_41<T> {
     // Manually implemented due to different bit layout
     pub fn get_dig_h4(&self) -> i16 {
         let arr = self.0.as_ref();
-        (arr[3] as i16) << 4 | (arr[4] & 0x0F) as i16
+        // 0xE4 holds signed upper 8 bits
+        (arr[3] as i8 as i16) << 4 | (arr[4] & 0x0F) as i16
     }
 }
 pub type Calib26_41Arr = Calib26_41<[u8; Calib26_41::<&[u8]>::REG_SIZE]>;
@@ -82,6 +88,10 @@ impl RegAddr for Reset {
 }
 impl Reset {
     pub const RESET_BYTE: u8 = 0xB6;
+    /// Time after reset before sensor answers on the bus
+    pub const STARTUP_MS: u16 = 2;
+    /// Upper bound for copying calibration from NVM after reset
+    pub const NVM_COPY_TIMEOUT_MS: u16 = 10;
 }
 
 bitfield! {
@@ -95,8 +105,8 @@ impl RegAddr for CtrlHum {
 
 bitfield! {
     pub struct Status(u8);
-    pub u8, get_im_update, _: 1, 0;
-    pub u8, get_measuring, _: 4, 3;
+    pub u8, get_im_update, _: 0, 0;
+    pub u8, get_measuring, _: 3, 3;
 }
 impl RegAddr for Status {
     const START_ADDR: u8 = 0xF3;
@@ -115,10 +125,10 @@ impl RegAddr for CtrlMeas {
 }
 
 bitfield! {
-    struct Config(u8);
-    u8, get_spi3w_en, set_spi3w_en: 0, 0;
-    u8, get_filter, set_filter: 4, 2;
-    u8, get_t_sb, set_t_sb: 7, 5;
+    pub struct Config(u8);
+    pub u8, get_spi3w_en, set_spi3w_en: 0, 0;
+    pub u8, get_filter, set_filter: 4, 2;
+    pub u8, get_t_sb, set_t_sb: 7, 5;
 }
 impl RegAddr for Config {
     const START_ADDR: u8 = 0xF5;
@@ -135,6 +145,12 @@ impl RegAddr for RawMeasures<[u8; 8]> {
     const START_ADDR: u8 = 0xF7;
     const END_ADDR: u8 = 0xFE;
 }
+impl RawMeasures<[u8; 8]> {
+    /// Temperature and pressure value reported when measurement is skipped
+    pub const SKIPPED_TEMP_PRESS: u32 = 0x80000;
+    /// Humidity value reported when measurement is skipped
+    pub const SKIPPED_HUM: u32 = 0x8000;
+}
 
 #[repr(u8)]
 #[derive(num_enum::TryFromPrimitive)]
@@ -145,20 +161,26 @@ pub enum Mode {
     Normal = 0b11,
 }
 
+/// Standby time between conversions in normal mode. Codes of the last two periods differ
+/// between chips: BMP280 reads them as 2000 ms and 4000 ms, so they are rejected there
 #[repr(u8)]
+#[derive(num_enum::TryFromPrimitive, Default, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum StandbyPeriod {
+    #[default]
     Us500 = 0b000,
     Us62500 = 0b001,
     Ms125 = 0b010,
     Ms250 = 0b011,
     Ms500 = 0b100,
     Ms1000 = 0b101,
+    /// BME280 only
     Ms10 = 0b110,
+    /// BME280 only
     Ms20 = 0b111,
 }
 
 #[repr(u8)]
-#[derive(num_enum::TryFromPrimitive, Default, Clone, Copy)]
+#[derive(num_enum::TryFromPrimitive, Default, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Oversampling {
     #[default]
     ModuleDisabled = 0b000,
@@ -169,9 +191,24 @@ pub enum Oversampling {
     X16 = 0b101,
 }
 
+impl Oversampling {
+    /// Number of samples taken per conversion, 0 if measurement is skipped
+    pub const fn factor(self) -> u32 {
+        match self {
+            Oversampling::ModuleDisabled => 0,
+            Oversampling::X1 => 1,
+            Oversampling::X2 => 2,
+            Oversampling::X4 => 4,
+            Oversampling::X8 => 8,
+            Oversampling::X16 => 16,
+        }
+    }
+}
+
 #[repr(u8)]
-#[derive(num_enum::TryFromPrimitive)]
+#[derive(num_enum::TryFromPrimitive, Default, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Filter {
+    #[default]
     Off = 0b000,
     C2 = 0b001,
     C4 = 0b010,
@@ -199,6 +236,13 @@ mod tests {
             (0, 1),
             (mock_status.get_im_update(), mock_status.get_measuring())
         );
+
+        // Reserved bits are ignored
+        let mock_status = Status(0b11110110);
+        assert_eq!(
+            (0, 0),
+            (mock_status.get_im_update(), mock_status.get_measuring())
+        );
     }
 
     #[test]
@@ -262,5 +306,19 @@ mod tests {
         assert_eq!(30, calib26_41.get_dig_h6());
     }
 
+    #[test]
+    fn test_config_parse() {
+        let mock_data = Config(0b10110001);
+        assert_eq!(1, mock_data.get_spi3w_en());
+        assert!(matches!(
+            Filter::try_from(mock_data.get_filter()).unwrap(),
+            Filter::C16
+        ));
+        assert!(matches!(
+            StandbyPeriod::try_from(mock_data.get_t_sb()).unwrap(),
+            StandbyPeriod::Ms1000
+        ));
+    }
+
     // TODO - add tests of raw measurements data parse
 }
@@ -0,0 +1,749 @@
+// Sensor logic shared by sync and async drivers, free of bus access. Driver operations are
+// sequences of register accesses, which each driver performs over its own bus
+
+use crate::submodules::registers::{
+    Calib00_25, Calib00_25Arr, Calib26_41, Calib26_41Arr, Config, CtrlHum, CtrlMeas, Id, Mode,
+    RawMeasures, RegAddr, RegSize, Reset, Status,
+};
+use crate::{
+    Bme280Config, Bme280Error, CalibData, ChipVariant, ConfigError, MeasurementMode, Oversampling,
+    StandbyPeriod,
+};
+
+pub(crate) type RawMeasuresArr = RawMeasures<[u8; RawMeasures::REG_SIZE]>;
+
+fn detect_chip<E>(id: u8) -> Result<ChipVariant, Bme280Error<E>> {
+    ChipVariant::from_id(id).ok_or(Bme280Error::IdDoesNotMatch)
+}
+
+// Reading `Id` register is all probing does, so sensor found keeps its state. Address not
+// answering counts as absent
+pub(crate) fn probed_chip<E>(id_read: Result<u8, E>) -> Option<ChipVariant> {
+    ChipVariant::from_id(Id(id_read.ok()?).get_id())
+}
+
+// Humidity can't be enabled on chips without humidity sensor. BMP280 reads t_sb codes of
+// 10 ms and 20 ms standby as 2000 ms and 4000 ms
+fn adjust_config<E>(
+    mut config: Bme280Config,
+    chip: ChipVariant,
+) -> Result<Bme280Config, Bme280Error<E>> {
+    if !chip.has_humidity() {
+        config.hum_oversampling = Oversampling::ModuleDisabled;
+    }
+    if chip == ChipVariant::Bmp280
+        && matches!(
+            config.standby_period,
+            StandbyPeriod::Ms10 | StandbyPeriod::Ms20
+        )
+    {
+        return Err(Bme280Error::InvalidConfig(ConfigError::StandbyNotSupported));
+    }
+    Ok(config)
+}
+
+fn check_calib<E>(calib_data: CalibData, chip: ChipVariant) -> Result<CalibData, Bme280Error<E>> {
+    if !calib_data.is_valid(chip) {
+        return Err(Bme280Error::InvalidCalibration);
+    }
+    Ok(calib_data)
+}
+
+// Humidity calibration is absent on BMP280, so `calib26_41` is left zeroed by `ReadCalib`
+fn parse_calib<E>(
+    calib00_25: Calib00_25Arr,
+    calib26_41: Calib26_41Arr,
+    chip: ChipVariant,
+) -> Result<CalibData, Bme280Error<E>> {
+    check_calib(CalibData::new(calib00_25, calib26_41), chip)
+}
+
+/// Register writes applying configuration, leaving sensor asleep. Writes to config may be
+/// ignored in normal mode, so sensor has to be put to sleep before them. Changes to ctrl_hum
+/// only take effect after following ctrl_meas write, so ctrl_meas goes last
+struct ConfigWrites {
+    ctrl_hum: CtrlHum,
+    ctrl_meas: CtrlMeas,
+    config: Config,
+    chip: ChipVariant,
+}
+
+impl ConfigWrites {
+    /// ctrl_hum, status, ctrl_meas and config are consecutive, so they are read back at once
+    const READ_BACK_ADDR: u8 = CtrlHum::START_ADDR;
+
+    fn new(config: &Bme280Config, chip: ChipVariant, spi3w: bool) -> Self {
+        let (ctrl_hum, ctrl_meas, config) = config.registers(spi3w);
+        Self {
+            ctrl_hum,
+            ctrl_meas,
+            config,
+            chip,
+        }
+    }
+
+    /// `(register, value)` pairs in write order
+    fn writes(&self) -> impl Iterator<Item = (u8, u8)> {
+        let ctrl_hum = self
+            .chip
+            .has_humidity()
+            .then_some((CtrlHum::START_ADDR, self.ctrl_hum.0));
+        [
+            Some((Config::START_ADDR, self.config.0)),
+            ctrl_hum,
+            Some((CtrlMeas::START_ADDR, self.ctrl_meas.0)),
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    /// Compare registers read back from [`Self::READ_BACK_ADDR`] against written ones,
+    /// ignoring reserved bits and status register
+    fn verify<E>(&self, read_back: &[u8]) -> Result<(), Bme280Error<E>> {
+        let hum_matches = !self.chip.has_humidity()
+            || CtrlHum(read_back[0]).get_oversampling() == self.ctrl_hum.0;
+        // Bit 1 of config is reserved
+        let matches = hum_matches
+            && read_back[2] == self.ctrl_meas.0
+            && read_back[3] & !0b10 == self.config.0;
+        if !matches {
+            return Err(Bme280Error::ConfigNotApplied);
+        }
+        Ok(())
+    }
+}
+
+/// Bounded polling of the sensor in 1 ms steps
+struct Deadline {
+    waited_ms: u32,
+    max_ms: u32,
+}
+
+impl Deadline {
+    /// Calibration copy from NVM, polled once sensor is up after reset
+    fn nvm_copy() -> Self {
+        Self {
+            waited_ms: Reset::STARTUP_MS as u32,
+            max_ms: Reset::NVM_COPY_TIMEOUT_MS as u32,
+        }
+    }
+
+    /// Forced conversion, polled after sleeping through its typical duration
+    fn measurement(config: &Bme280Config) -> Self {
+        let time = config.measurement_time();
+        Self {
+            waited_ms: time.typical_us.div_ceil(1000),
+            max_ms: time.max_us.div_ceil(1000),
+        }
+    }
+
+    /// Time to wait before first poll
+    fn waited_ms(&self) -> u32 {
+        self.waited_ms
+    }
+
+    /// Accounts for another 1 ms wait, `false` once the deadline is reached
+    fn next(&mut self) -> bool {
+        if self.waited_ms >= self.max_ms {
+            return false;
+        }
+        self.waited_ms += 1;
+        true
+    }
+}
+
+/// Bus access requested by a [`Sequence`]
+pub(crate) enum Access {
+    /// Read given number of consecutive registers, starting at given address
+    Read(u8, usize),
+    Write(u8, u8),
+    DelayMs(u32),
+}
+
+pub(crate) enum Step<T> {
+    Access(Access),
+    Done(T),
+}
+
+/// Outcome of the previous access: registers read (empty for writes and delays) or bus error
+pub(crate) type Last<'a, E> = Result<&'a [u8], Bme280Error<E>>;
+
+/// Driver operation as a state machine, advanced by sync and async drivers one bus access at
+/// a time. First step gets `Ok(&[])`, driver stops at the first error returned
+pub(crate) trait Sequence<E> {
+    type Output;
+
+    fn step(&mut self, last: Last<'_, E>) -> Result<Step<Self::Output>, Bme280Error<E>>;
+}
+
+/// Longest read a [`Sequence`] asks for
+pub(crate) const MAX_READ_LEN: usize = Calib00_25Arr::REG_SIZE;
+
+// Passes accesses of a nested sequence through, evaluates to its output once it is done
+macro_rules! nested {
+    ($seq:expr, $last:expr) => {
+        match Sequence::<E>::step($seq, $last)? {
+            Step::Access(access) => return Ok(Step::Access(access)),
+            Step::Done(output) => output,
+        }
+    };
+}
+
+/// Single read of consecutive registers
+struct ReadRegs {
+    addr: u8,
+    len: usize,
+    sent: bool,
+}
+
+impl ReadRegs {
+    fn new(addr: u8, len: usize) -> Self {
+        Self {
+            addr,
+            len,
+            sent: false,
+        }
+    }
+}
+
+impl<E> Sequence<E> for ReadRegs {
+    type Output = [u8; MAX_READ_LEN];
+
+    fn step(&mut self, last: Last<'_, E>) -> Result<Step<Self::Output>, Bme280Error<E>> {
+        let read = last?;
+        if !self.sent {
+            self.sent = true;
+            return Ok(Step::Access(Access::Read(self.addr, self.len)));
+        }
+        let mut regs = [0; MAX_READ_LEN];
+        regs[..read.len()].copy_from_slice(read);
+        Ok(Step::Done(regs))
+    }
+}
+
+pub(crate) struct ReadChip(ReadRegs);
+
+impl ReadChip {
+    pub(crate) fn new() -> Self {
+        Self(ReadRegs::new(Id::START_ADDR, Id::REG_SIZE))
+    }
+}
+
+impl<E> Sequence<E> for ReadChip {
+    type Output = ChipVariant;
+
+    fn step(&mut self, last: Last<'_, E>) -> Result<Step<ChipVariant>, Bme280Error<E>> {
+        let regs = nested!(&mut self.0, last);
+        Ok(Step::Done(detect_chip(Id(regs[0]).get_id())?))
+    }
+}
+
+pub(crate) struct ReadRaw(ReadRegs);
+
+impl ReadRaw {
+    pub(crate) fn new() -> Self {
+        Self(ReadRegs::new(
+            RawMeasures::START_ADDR,
+            RawMeasures::REG_SIZE,
+        ))
+    }
+}
+
+impl<E> Sequence<E> for ReadRaw {
+    type Output = RawMeasuresArr;
+
+    fn step(&mut self, last: Last<'_, E>) -> Result<Step<RawMeasuresArr>, Bme280Error<E>> {
+        let regs = nested!(&mut self.0, last);
+        let mut raw_measures = RawMeasures([0; RawMeasures::REG_SIZE]);
+        raw_measures
+            .0
+            .copy_from_slice(&regs[..RawMeasures::REG_SIZE]);
+        Ok(Step::Done(raw_measures))
+    }
+}
+
+/// Calibration read and validated. Humidity calibration is absent on BMP280, its coefficients
+/// are left zeroed
+pub(crate) struct ReadCalib {
+    chip: ChipVariant,
+    state: ReadCalibState,
+}
+
+enum ReadCalibState {
+    Calib00_25(ReadRegs),
+    Calib26_41(ReadRegs, [u8; Calib00_25Arr::REG_SIZE]),
+}
+
+impl ReadCalib {
+    pub(crate) fn new(chip: ChipVariant) -> Self {
+        Self {
+            chip,
+            state: ReadCalibState::Calib00_25(ReadRegs::new(
+                Calib00_25Arr::START_ADDR,
+                Calib00_25Arr::REG_SIZE,
+            )),
+        }
+    }
+}
+
+impl<E> Sequence<E> for ReadCalib {
+    type Output = CalibData;
+
+    fn step(&mut self, last: Last<'_, E>) -> Result<Step<CalibData>, Bme280Error<E>> {
+        let (calib00_25, calib26_41) = match &mut self.state {
+            ReadCalibState::Calib00_25(seq) => {
+                let regs = nested!(seq, last);
+                let mut calib00_25 = [0; Calib00_25Arr::REG_SIZE];
+                calib00_25.copy_from_slice(&regs[..Calib00_25Arr::REG_SIZE]);
+                if self.chip.has_humidity() {
+                    let seq = ReadRegs::new(Calib26_41Arr::START_ADDR, Calib26_41Arr::REG_SIZE);
+                    self.state = ReadCalibState::Calib26_41(seq, calib00_25);
+                    return self.step(Ok(&[]));
+                }
+                (calib00_25, [0; Calib26_41Arr::REG_SIZE])
+            }
+            ReadCalibState::Calib26_41(seq, calib00_25) => {
+                let regs = nested!(seq, last);
+                let mut calib26_41 = [0; Calib26_41Arr::REG_SIZE];
+                calib26_41.copy_from_slice(&regs[..Calib26_41Arr::REG_SIZE]);
+                (*calib00_25, calib26_41)
+            }
+        };
+        let calib_data = parse_calib(Calib00_25(calib00_25), Calib26_41(calib26_41), self.chip)?;
+        Ok(Step::Done(calib_data))
+    }
+}
+
+/// Mode change, keeping oversampling settings sharing ctrl_meas
+pub(crate) struct SetMode {
+    mode: u8,
+    state: SetModeState,
+}
+
+enum SetModeState {
+    Read(ReadRegs),
+    Written,
+}
+
+impl SetMode {
+    pub(crate) fn new(mode: Mode) -> Self {
+        Self {
+            mode: mode as u8,
+            state: SetModeState::Read(ReadRegs::new(CtrlMeas::START_ADDR, CtrlMeas::REG_SIZE)),
+        }
+    }
+}
+
+impl<E> Sequence<E> for SetMode {
+    type Output = ();
+
+    fn step(&mut self, last: Last<'_, E>) -> Result<Step<()>, Bme280Error<E>> {
+        match &mut self.state {
+            SetModeState::Read(seq) => {
+                let regs = nested!(seq, last);
+                let mut ctrl_meas = CtrlMeas(regs[0]);
+                ctrl_meas.set_mode(self.mode);
+                self.state = SetModeState::Written;
+                Ok(Step::Access(Access::Write(
+                    CtrlMeas::START_ADDR,
+                    ctrl_meas.0,
+                )))
+            }
+            SetModeState::Written => {
+                last?;
+                Ok(Step::Done(()))
+            }
+        }
+    }
+}
+
+/// Soft reset, waiting for calibration to be copied from NVM
+pub(crate) struct ResetSeq {
+    spi3w: bool,
+    deadline: Deadline,
+    state: ResetState,
+}
+
+enum ResetState {
+    Start,
+    Written,
+    Started,
+    Waited,
+    Polled,
+}
+
+impl ResetSeq {
+    pub(crate) fn new(spi3w: bool) -> Self {
+        Self {
+            spi3w,
+            deadline: Deadline::nvm_copy(),
+            state: ResetState::Start,
+        }
+    }
+}
+
+impl<E> Sequence<E> for ResetSeq {
+    type Output = ();
+
+    fn step(&mut self, last: Last<'_, E>) -> Result<Step<()>, Bme280Error<E>> {
+        let read = last?;
+        let access = match self.state {
+            ResetState::Start => {
+                let mut reset = Reset(0);
+                reset.set_reset(Reset::RESET_BYTE);
+                self.state = ResetState::Written;
+                Access::Write(Reset::START_ADDR, reset.0)
+            }
+            ResetState::Written => {
+                self.state = ResetState::Started;
+                Access::DelayMs(self.deadline.waited_ms())
+            }
+            // Reset disables 3-wire SPI, and sensor won't answer on SDI line until it is set back
+            ResetState::Started if self.spi3w => {
+                let mut config = Config(0);
+                config.set_spi3w_en(1);
+                self.state = ResetState::Waited;
+                Access::Write(Config::START_ADDR, config.0)
+            }
+            ResetState::Started | ResetState::Waited => {
+                self.state = ResetState::Polled;
+                Access::Read(Status::START_ADDR, Status::REG_SIZE)
+            }
+            // Calibration is garbage until it is copied from NVM
+            ResetState::Polled => {
+                if Status(read[0]).get_im_update() == 0 {
+                    return Ok(Step::Done(()));
+                }
+                if !self.deadline.next() {
+                    return Err(Bme280Error::NvmCopyTimeout);
+                }
+                self.state = ResetState::Waited;
+                Access::DelayMs(1)
+            }
+        };
+        Ok(Step::Access(access))
+    }
+}
+
+/// Configuration applied and read back, then sensor is restarted if it asks for normal mode
+pub(crate) struct ApplyConfig {
+    writes: ConfigWrites,
+    normal: bool,
+    state: ApplyState,
+}
+
+enum ApplyState {
+    Sleep(SetMode),
+    Write(usize),
+    ReadBack(ReadRegs),
+    StartNormal(SetMode),
+}
+
+impl ApplyConfig {
+    pub(crate) fn new(config: &Bme280Config, chip: ChipVariant, spi3w: bool) -> Self {
+        Self {
+            writes: ConfigWrites::new(config, chip, spi3w),
+            normal: config.mode == MeasurementMode::Normal,
+            state: ApplyState::Sleep(SetMode::new(Mode::Sleep)),
+        }
+    }
+}
+
+impl<E> Sequence<E> for ApplyConfig {
+    type Output = ();
+
+    fn step(&mut self, mut last: Last<'_, E>) -> Result<Step<()>, Bme280Error<E>> {
+        loop {
+            match &mut self.state {
+                ApplyState::Sleep(seq) => {
+                    nested!(seq, last);
+                    self.state = ApplyState::Write(0);
+                }
+                ApplyState::Write(written) => {
+                    last?;
+                    if let Some((reg_addr, data)) = self.writes.writes().nth(*written) {
+                        *written += 1;
+                        return Ok(Step::Access(Access::Write(reg_addr, data)));
+                    }
+                    self.state =
+                        ApplyState::ReadBack(ReadRegs::new(ConfigWrites::READ_BACK_ADDR, 4));
+                }
+                ApplyState::ReadBack(seq) => {
+                    let regs = nested!(seq, last);
+                    self.writes.verify(&regs[..4])?;
+                    if !self.normal {
+                        return Ok(Step::Done(()));
+                    }
+                    self.state = ApplyState::StartNormal(SetMode::new(Mode::Normal));
+                }
+                ApplyState::StartNormal(seq) => {
+                    nested!(seq, last);
+                    return Ok(Step::Done(()));
+                }
+            }
+            last = Ok(&[]);
+        }
+    }
+}
+
+/// Sensor reset, identified and configured, with calibration read unless it is given.
+/// Outputs chip found, its calibration and configuration adjusted to it
+pub(crate) struct Init {
+    config: Bme280Config,
+    calib_data: Option<CalibData>,
+    spi3w: bool,
+    state: InitState,
+}
+
+enum InitState {
+    Reset(ResetSeq),
+    ReadChip(ReadChip),
+    ReadCalib(ReadCalib),
+    Apply(ApplyConfig, ChipVariant, CalibData),
+}
+
+impl Init {
+    pub(crate) fn new<E>(
+        config: Bme280Config,
+        calib_data: Option<CalibData>,
+        spi3w: bool,
+    ) -> Result<Self, Bme280Error<E>> {
+        config.validate().map_err(Bme280Error::InvalidConfig)?;
+        Ok(Self {
+            config,
+            calib_data,
+            spi3w,
+            state: InitState::Reset(ResetSeq::new(spi3w)),
+        })
+    }
+
+    fn apply(&mut self, chip: ChipVariant, calib_data: CalibData) {
+        let seq = ApplyConfig::new(&self.config, chip, self.spi3w);
+        self.state = InitState::Apply(seq, chip, calib_data);
+    }
+}
+
+impl<E> Sequence<E> for Init {
+    type Output = (ChipVariant, CalibData, Bme280Config);
+
+    fn step(&mut self, mut last: Last<'_, E>) -> Result<Step<Self::Output>, Bme280Error<E>> {
+        loop {
+            match &mut self.state {
+                InitState::Reset(seq) => {
+                    nested!(seq, last);
+                    self.state = InitState::ReadChip(ReadChip::new());
+                }
+                InitState::ReadChip(seq) => {
+                    let chip = nested!(seq, last);
+                    self.config = adjust_config(self.config, chip)?;
+                    match self.calib_data {
+                        Some(calib_data) => self.apply(chip, check_calib(calib_data, chip)?),
+                        None => self.state = InitState::ReadCalib(ReadCalib::new(chip)),
+                    }
+                }
+                InitState::ReadCalib(seq) => {
+                    let calib_data = nested!(seq, last);
+                    let chip = seq.chip;
+                    self.apply(chip, calib_data);
+                }
+                InitState::Apply(seq, chip, calib_data) => {
+                    nested!(seq, last);
+                    return Ok(Step::Done((*chip, *calib_data, self.config)));
+                }
+            }
+            last = Ok(&[]);
+        }
+    }
+}
+
+/// New configuration applied at runtime, outputs it adjusted to the chip. On failure previous
+/// configuration is applied again and the error is reported
+pub(crate) struct Reconfigure<E> {
+    config: Bme280Config,
+    old_config: Bme280Config,
+    apply: ApplyConfig,
+    restore: ApplyConfig,
+    error: Option<Bme280Error<E>>,
+}
+
+impl<E> Reconfigure<E> {
+    pub(crate) fn new(
+        config: Bme280Config,
+        old_config: Bme280Config,
+        chip: ChipVariant,
+        spi3w: bool,
+    ) -> Result<Self, Bme280Error<E>> {
+        config.validate().map_err(Bme280Error::InvalidConfig)?;
+        let config = adjust_config(config, chip)?;
+        Ok(Self {
+            config,
+            old_config,
+            apply: ApplyConfig::new(&config, chip, spi3w),
+            restore: ApplyConfig::new(&old_config, chip, spi3w),
+            error: None,
+        })
+    }
+}
+
+impl<E> Sequence<E> for Reconfigure<E> {
+    type Output = Bme280Config;
+
+    fn step(&mut self, mut last: Last<'_, E>) -> Result<Step<Bme280Config>, Bme280Error<E>> {
+        if self.error.is_none() {
+            match Sequence::<E>::step(&mut self.apply, last) {
+                Ok(Step::Access(access)) => return Ok(Step::Access(access)),
+                Ok(Step::Done(())) => return Ok(Step::Done(self.config)),
+                Err(e) => {
+                    self.error = Some(e);
+                    last = Ok(&[]);
+                }
+            }
+        }
+        // Sensor may be left half-configured, best effort to get back to known state
+        if let Ok(Step::Access(access)) = Sequence::<E>::step(&mut self.restore, last) {
+            return Ok(Step::Access(access));
+        }
+        self.error
+            .take()
+            .map_or(Ok(Step::Done(self.old_config)), Err)
+    }
+}
+
+/// Reset followed by calibration read and configuration applied again, outputs calibration
+pub(crate) struct SoftReset {
+    config: Bme280Config,
+    spi3w: bool,
+    state: SoftResetState,
+}
+
+enum SoftResetState {
+    Reset(ResetSeq, ChipVariant),
+    ReadCalib(ReadCalib),
+    Apply(ApplyConfig, CalibData),
+}
+
+impl SoftReset {
+    pub(crate) fn new(config: Bme280Config, chip: ChipVariant, spi3w: bool) -> Self {
+        Self {
+            config,
+            spi3w,
+            state: SoftResetState::Reset(ResetSeq::new(spi3w), chip),
+        }
+    }
+}
+
+impl<E> Sequence<E> for SoftReset {
+    type Output = CalibData;
+
+    fn step(&mut self, mut last: Last<'_, E>) -> Result<Step<CalibData>, Bme280Error<E>> {
+        loop {
+            match &mut self.state {
+                SoftResetState::Reset(seq, chip) => {
+                    nested!(seq, last);
+                    self.state = SoftResetState::ReadCalib(ReadCalib::new(*chip));
+                }
+                SoftResetState::ReadCalib(seq) => {
+                    let calib_data = nested!(seq, last);
+                    let seq = ApplyConfig::new(&self.config, seq.chip, self.spi3w);
+                    self.state = SoftResetState::Apply(seq, calib_data);
+                }
+                SoftResetState::Apply(seq, calib_data) => {
+                    nested!(seq, last);
+                    return Ok(Step::Done(*calib_data));
+                }
+            }
+            last = Ok(&[]);
+        }
+    }
+}
+
+/// Forced conversion triggered and waited for, outputs its raw result
+pub(crate) struct Measure {
+    deadline: Deadline,
+    state: MeasureState,
+}
+
+enum MeasureState {
+    Trigger(SetMode),
+    Waited,
+    Polled,
+    Read(ReadRaw),
+}
+
+impl Measure {
+    pub(crate) fn new(config: &Bme280Config) -> Self {
+        Self {
+            deadline: Deadline::measurement(config),
+            state: MeasureState::Trigger(SetMode::new(Mode::Forced)),
+        }
+    }
+}
+
+impl<E> Sequence<E> for Measure {
+    type Output = RawMeasuresArr;
+
+    fn step(&mut self, last: Last<'_, E>) -> Result<Step<RawMeasuresArr>, Bme280Error<E>> {
+        let access = match &mut self.state {
+            // Sleep through most of the conversion, then poll for its end
+            MeasureState::Trigger(seq) => {
+                nested!(seq, last);
+                self.state = MeasureState::Waited;
+                Access::DelayMs(self.deadline.waited_ms())
+            }
+            MeasureState::Waited => {
+                last?;
+                self.state = MeasureState::Polled;
+                Access::Read(Status::START_ADDR, Status::REG_SIZE)
+            }
+            MeasureState::Polled => {
+                if Status(last?[0]).get_measuring() == 0 {
+                    self.state = MeasureState::Read(ReadRaw::new());
+                    return self.step(Ok(&[]));
+                }
+                if !self.deadline.next() {
+                    return Err(Bme280Error::Timeout);
+                }
+                self.state = MeasureState::Waited;
+                Access::DelayMs(1)
+            }
+            MeasureState::Read(seq) => return Ok(Step::Done(nested!(seq, last))),
+        };
+        Ok(Step::Access(access))
+    }
+}
+
+/// Single check whether triggered conversion is done, outputs its raw result if so
+pub(crate) struct PollMeasurement {
+    state: PollState,
+}
+
+enum PollState {
+    Status(ReadRegs),
+    Read(ReadRaw),
+}
+
+impl PollMeasurement {
+    pub(crate) fn new() -> Self {
+        Self {
+            state: PollState::Status(ReadRegs::new(Status::START_ADDR, Status::REG_SIZE)),
+        }
+    }
+}
+
+impl<E> Sequence<E> for PollMeasurement {
+    type Output = Option<RawMeasuresArr>;
+
+    fn step(&mut self, last: Last<'_, E>) -> Result<Step<Self::Output>, Bme280Error<E>> {
+        match &mut self.state {
+            PollState::Status(seq) => {
+                let regs = nested!(seq, last);
+                if Status(regs[0]).get_measuring() == 1 {
+                    return Ok(Step::Done(None));
+                }
+                self.state = PollState::Read(ReadRaw::new());
+                self.step(Ok(&[]))
+            }
+            PollState::Read(seq) => Ok(Step::Done(Some(nested!(seq, last)))),
+        }
+    }
+}
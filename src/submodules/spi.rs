@@ -0,0 +1,311 @@
+use crate::submodules::registers::SPI_READ_FLAG;
+use crate::{RegRead, RegWrite};
+use embedded_hal::blocking::spi::{Transfer, Write};
+use embedded_hal::digital::v2::OutputPin;
+
+/// 4-wire SPI transport. Device address passed to the driver is ignored, chip is selected by `cs`
+pub struct SpiInterface<SPI, CS> {
+    spi: SPI,
+    cs: CS,
+}
+
+#[derive(Debug)]
+pub enum SpiError<SpiE, PinE> {
+    Spi(SpiE),
+    Pin(PinE),
+}
+
+impl<SPI, CS> SpiInterface<SPI, CS> {
+    pub fn new(spi: SPI, cs: CS) -> Self {
+        Self { spi, cs }
+    }
+
+    pub fn release(self) -> (SPI, CS) {
+        (self.spi, self.cs)
+    }
+}
+
+/// 3-wire SPI transport, data is both sent and received over single SDI line.
+/// Driver enables 3-wire mode in the sensor (`spi3w_en`) right after reset
+pub struct Spi3WireInterface<SPI, CS> {
+    spi: SPI,
+    cs: CS,
+}
+
+/// Half-duplex SPI bus with bidirectional data line, as found on peripherals that
+/// support 3-wire (bidirectional) mode
+pub trait HalfDuplexBus {
+    type Error;
+
+    /// Drive data line and send `words`
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error>;
+    /// Release data line and clock in `words`
+    fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error>;
+}
+
+impl<SPI, CS> Spi3WireInterface<SPI, CS> {
+    pub fn new(spi: SPI, cs: CS) -> Self {
+        Self { spi, cs }
+    }
+
+    pub fn release(self) -> (SPI, CS) {
+        (self.spi, self.cs)
+    }
+}
+
+fn transaction<SPI, CS, F, SpiE, PinE>(
+    spi: &mut SPI,
+    cs: &mut CS,
+    f: F,
+) -> Result<(), SpiError<SpiE, PinE>>
+where
+    CS: OutputPin<Error = PinE>,
+    F: FnOnce(&mut SPI) -> Result<(), SpiE>,
+{
+    cs.set_low().map_err(SpiError::Pin)?;
+    let res = f(spi).map_err(SpiError::Spi);
+    // Release chip select even if transfer failed
+    cs.set_high().map_err(SpiError::Pin)?;
+    res
+}
+
+impl<SPI, CS, SpiE, PinE> RegRead for SpiInterface<SPI, CS>
+where
+    SPI: Transfer<u8, Error = SpiE> + Write<u8, Error = SpiE>,
+    CS: OutputPin<Error = PinE>,
+{
+    type Error = SpiError<SpiE, PinE>;
+
+    fn reg_read(&mut self, _dev_addr: u8, reg_addr: u8, buf: &mut [u8]) -> Result<(), Self::Error> {
+        // Register address is auto-incremented, so whole block is read in one burst
+        transaction(&mut self.spi, &mut self.cs, |spi| {
+            spi.write(&[reg_addr | SPI_READ_FLAG])?;
+            spi.transfer(buf)?;
+            Ok(())
+        })
+    }
+}
+
+impl<SPI, CS, SpiE, PinE> RegWrite for SpiInterface<SPI, CS>
+where
+    SPI: Transfer<u8, Error = SpiE> + Write<u8, Error = SpiE>,
+    CS: OutputPin<Error = PinE>,
+{
+    type Error = SpiError<SpiE, PinE>;
+
+    fn reg_write(&mut self, _dev_addr: u8, reg_addr: u8, data: u8) -> Result<(), Self::Error> {
+        transaction(&mut self.spi, &mut self.cs, |spi| {
+            spi.write(&[reg_addr & !SPI_READ_FLAG, data])
+        })
+    }
+}
+
+impl<SPI, CS, SpiE, PinE> RegRead for Spi3WireInterface<SPI, CS>
+where
+    SPI: HalfDuplexBus<Error = SpiE>,
+    CS: OutputPin<Error = PinE>,
+{
+    type Error = SpiError<SpiE, PinE>;
+
+    fn reg_read(&mut self, _dev_addr: u8, reg_addr: u8, buf: &mut [u8]) -> Result<(), Self::Error> {
+        transaction(&mut self.spi, &mut self.cs, |spi| {
+            spi.write(&[reg_addr | SPI_READ_FLAG])?;
+            spi.read(buf)
+        })
+    }
+}
+
+impl<SPI, CS, SpiE, PinE> RegWrite for Spi3WireInterface<SPI, CS>
+where
+    SPI: HalfDuplexBus<Error = SpiE>,
+    CS: OutputPin<Error = PinE>,
+{
+    type Error = SpiError<SpiE, PinE>;
+    const SPI_3W: bool = true;
+
+    fn reg_write(&mut self, _dev_addr: u8, reg_addr: u8, data: u8) -> Result<(), Self::Error> {
+        transaction(&mut self.spi, &mut self.cs, |spi| {
+            spi.write(&[reg_addr & !SPI_READ_FLAG, data])
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{mock_regs, NoopDelay};
+    use crate::{Bme280, Bme280Config, Oversampling};
+    use core::cell::RefCell;
+
+    // Simulated BME280 SPI slave
+    struct MockBus {
+        regs: [u8; 256],
+        cs_low: bool,
+        // Current register address and whether it is being read
+        ctrl: Option<(u8, bool)>,
+        transactions: usize,
+    }
+
+    impl MockBus {
+        fn new() -> RefCell<Self> {
+            RefCell::new(Self {
+                regs: mock_regs(),
+                cs_low: false,
+                ctrl: None,
+                transactions: 0,
+            })
+        }
+    }
+
+    struct MockSpi<'a>(&'a RefCell<MockBus>);
+    struct MockCs<'a>(&'a RefCell<MockBus>);
+
+    impl Write<u8> for MockSpi<'_> {
+        type Error = ();
+
+        fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+            let mut bus = self.0.borrow_mut();
+            assert!(bus.cs_low, "SPI write with chip deselected");
+            for &word in words {
+                match bus.ctrl {
+                    None => bus.ctrl = Some((word | 0x80, word & 0x80 != 0)),
+                    Some((addr, false)) => {
+                        bus.regs[addr as usize] = word;
+                        if addr == 0xE0 && word == 0xB6 {
+                            // Reset clears config, including spi3w_en
+                            bus.regs[0xF5] = 0;
+                        }
+                        bus.ctrl = None;
+                    }
+                    Some((_, true)) => {}
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl Transfer<u8> for MockSpi<'_> {
+        type Error = ();
+
+        fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Self::Error> {
+            let mut bus = self.0.borrow_mut();
+            assert!(bus.cs_low, "SPI transfer with chip deselected");
+            let Some((mut addr, true)) = bus.ctrl else {
+                panic!("SPI transfer outside of read");
+            };
+            for word in words.iter_mut() {
+                *word = bus.regs[addr as usize];
+                addr = addr.wrapping_add(1);
+            }
+            bus.ctrl = Some((addr, true));
+            Ok(words)
+        }
+    }
+
+    impl HalfDuplexBus for MockSpi<'_> {
+        type Error = ();
+
+        fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+            Write::write(self, words)
+        }
+
+        fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+            if self.0.borrow().regs[0xF5] & 0x01 == 0 {
+                // Sensor drives SDO, which is not connected, so data line stays pulled up
+                words.fill(0xFF);
+                return Ok(());
+            }
+            self.transfer(words)?;
+            Ok(())
+        }
+    }
+
+    impl OutputPin for MockCs<'_> {
+        type Error = ();
+
+        fn set_low(&mut self) -> Result<(), Self::Error> {
+            let mut bus = self.0.borrow_mut();
+            bus.cs_low = true;
+            bus.transactions += 1;
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Self::Error> {
+            let mut bus = self.0.borrow_mut();
+            bus.cs_low = false;
+            bus.ctrl = None;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_reg_read_burst() {
+        let bus = MockBus::new();
+        let mut spi = SpiInterface::new(MockSpi(&bus), MockCs(&bus));
+
+        let mut buf = [0; 8];
+        spi.reg_read(0, 0xF7, &mut buf).unwrap();
+        assert_eq!([0x4E, 0xD2, 0xA0, 0x80, 0x8B, 0x20, 0x5B, 0xFD], buf);
+        assert_eq!(1, bus.borrow().transactions);
+        assert!(!bus.borrow().cs_low);
+    }
+
+    #[test]
+    fn test_reg_write() {
+        let bus = MockBus::new();
+        let mut spi = SpiInterface::new(MockSpi(&bus), MockCs(&bus));
+
+        spi.reg_write(0, 0xF4, 0x27).unwrap();
+        assert_eq!(0x27, bus.borrow().regs[0xF4]);
+        assert_eq!(1, bus.borrow().transactions);
+    }
+
+    #[test]
+    fn test_init_over_spi() {
+        let bus = MockBus::new();
+        let spi = SpiInterface::new(MockSpi(&bus), MockCs(&bus));
+        let config = Bme280Config {
+            hum_oversampling: Oversampling::X1,
+            temp_oversampling: Oversampling::X1,
+            press_oversampling: Oversampling::X1,
+            ..Default::default()
+        };
+
+        let mut bme280 = Bme280::init(spi, 0, NoopDelay {}, config).unwrap();
+        assert_eq!(28765, bme280.get_calib().dig_t1);
+        assert_eq!(293, bme280.get_calib().dig_h4);
+
+        let measurement = bme280.do_measurement().unwrap();
+        assert_eq!(21, measurement.temperature.unwrap().get_int());
+        assert_eq!(0b00100101, bus.borrow().regs[0xF4]);
+    }
+
+    #[test]
+    fn test_3wire_requires_spi3w_en() {
+        let bus = MockBus::new();
+        let mut spi = Spi3WireInterface::new(MockSpi(&bus), MockCs(&bus));
+
+        let mut buf = [0; 1];
+        spi.reg_read(0, 0xD0, &mut buf).unwrap();
+        assert_eq!([0xFF], buf);
+    }
+
+    #[test]
+    fn test_init_over_3wire_spi() {
+        let bus = MockBus::new();
+        let spi = Spi3WireInterface::new(MockSpi(&bus), MockCs(&bus));
+        let config = Bme280Config {
+            hum_oversampling: Oversampling::X1,
+            temp_oversampling: Oversampling::X1,
+            press_oversampling: Oversampling::X1,
+            ..Default::default()
+        };
+
+        let mut bme280 = Bme280::init(spi, 0, NoopDelay {}, config).unwrap();
+        assert_eq!(28765, bme280.get_calib().dig_t1);
+        assert_eq!(0x01, bus.borrow().regs[0xF5]);
+
+        let measurement = bme280.do_measurement().unwrap();
+        assert_eq!(21, measurement.temperature.unwrap().get_int());
+    }
+}